@@ -0,0 +1,770 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::Write,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{Context, Result, anyhow};
+use futures::{StreamExt, stream::BoxStream};
+use serde_json::{Map, Value, json};
+
+use crate::{
+    Api, drop_below_score, exa_post, output::project_fields, project_result, record::canonicalize,
+};
+
+pub(crate) struct ContentsBatch<'a> {
+    pub(crate) batch_size: usize,
+    pub(crate) concurrency: usize,
+    /// Under `--stream-results`, where each result line is written as soon
+    /// as its batch lands.
+    pub(crate) stream: Option<Stream<'a>>,
+    pub(crate) ordered: bool,
+    pub(crate) max_results: Option<usize>,
+    pub(crate) project: Option<&'a [&'a str]>,
+    /// On Ctrl-C, stop sending batches and return what was collected.
+    pub(crate) partial_on_interrupt: bool,
+}
+
+pub(crate) struct Stream<'a> {
+    pub(crate) out: Box<dyn Write>,
+    /// `--fields` to project each line to; empty keeps whole results.
+    pub(crate) fields: &'a [String],
+}
+
+pub(crate) enum ContentsOutcome {
+    Merged {
+        payload: Value,
+        /// Batch requests that failed outright; their urls/ids are listed
+        /// as error statuses next to the other batches' results.
+        failed_batches: usize,
+    },
+    /// Ctrl-C arrived under `partial_on_interrupt`; holds the batches that
+    /// had finished.
+    Interrupted(Value),
+    Streamed {
+        failed: usize,
+        /// Batch requests that failed outright, counted in `failed` once
+        /// per url/id.
+        failed_batches: usize,
+    },
+}
+
+/// Set while a batch will flush its partial results on Ctrl-C, so `main`
+/// waits for it instead of exiting.
+static FLUSH_ON_INTERRUPT: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn flushes_on_interrupt() -> bool {
+    FLUSH_ON_INTERRUPT.load(Ordering::SeqCst)
+}
+
+/// Clears `FLUSH_ON_INTERRUPT` when a batch returns, errors included, so a
+/// later Ctrl-C exits right away again.
+struct FlushGuard;
+
+impl Drop for FlushGuard {
+    fn drop(&mut self) {
+        FLUSH_ON_INTERRUPT.store(false, Ordering::SeqCst);
+    }
+}
+
+pub(crate) async fn contents(
+    api: &Api,
+    body: Map<String, Value>,
+    mut opts: ContentsBatch<'_>,
+) -> Result<ContentsOutcome> {
+    let bodies = chunk_targets(body, opts.batch_size);
+    let total = bodies.len();
+    let requests = futures::stream::iter(bodies.into_iter().map(|body| async move {
+        let targets: Vec<Value> = ["urls", "ids"]
+            .iter()
+            .filter_map(|key| body.get(*key).and_then(Value::as_array))
+            .flatten()
+            .cloned()
+            .collect();
+        (
+            targets,
+            exa_post(api, "/contents", Value::Object(body)).await,
+        )
+    }));
+    let mut responses: BoxStream<'_, (Vec<Value>, Result<Value>)> =
+        if opts.stream.is_some() && !opts.ordered {
+            requests.buffer_unordered(opts.concurrency).boxed()
+        } else {
+            requests.buffered(opts.concurrency).boxed()
+        };
+
+    let max = opts.max_results.unwrap_or(usize::MAX);
+    let (mut batches, mut results, mut statuses) = (0, Vec::new(), Vec::new());
+    let mut emitted = 0;
+    let interrupt = tokio::signal::ctrl_c();
+    tokio::pin!(interrupt);
+    let listen = opts.partial_on_interrupt && opts.stream.is_none();
+    FLUSH_ON_INTERRUPT.store(listen, Ordering::SeqCst);
+    let _flush = FlushGuard;
+    let mut interrupted = false;
+    let (mut failed_batches, mut last_err) = (0, None);
+    while emitted < max {
+        let response = if listen {
+            tokio::select! {
+                response = responses.next() => response,
+                Ok(()) = &mut interrupt => {
+                    interrupted = true;
+                    break;
+                }
+            }
+        } else {
+            responses.next().await
+        };
+        let Some((targets, response)) = response else {
+            break;
+        };
+        let mut response = match response {
+            Ok(response) => response,
+            Err(err) => {
+                // Every url/id of the batch becomes an error status, so it is
+                // counted and listed like a page that failed to crawl.
+                eprintln!("contents batch failed: {err:#}");
+                let message = format!("{err:#}");
+                statuses.extend(targets.into_iter().map(
+                    |id| json!({ "id": id, "status": "error", "error": { "message": message } }),
+                ));
+                failed_batches += 1;
+                last_err = Some(err);
+                continue;
+            }
+        };
+        batches += 1;
+        for mut result in take_array(&mut response, "results") {
+            if emitted == max {
+                break;
+            }
+            emitted += 1;
+            if let Some(fields) = opts.project {
+                project_result(&mut result, fields);
+            }
+            match &mut opts.stream {
+                Some(stream) => stream.line(&result)?,
+                None => results.push(result),
+            }
+        }
+        statuses.extend(take_array(&mut response, "statuses"));
+    }
+
+    report_breaker(api, total);
+    if let Some(stream) = &mut opts.stream {
+        let failed = statuses
+            .iter()
+            .filter(|status| status.get("status").and_then(Value::as_str) == Some("error"))
+            .count();
+        let summary = json!({ "summary": {
+            "batches": batches,
+            "results": emitted,
+            "failed": failed,
+            "failed_batches": failed_batches,
+        } });
+        stream.write(&summary)?;
+        if batches > 0 || last_err.is_none() {
+            return Ok(ContentsOutcome::Streamed {
+                failed,
+                failed_batches,
+            });
+        }
+    }
+    if let Some(err) = last_err
+        && batches == 0
+    {
+        return Err(err.context(format!(
+            "no contents batch succeeded ({failed_batches} failed)"
+        )));
+    }
+    let payload = json!({ "results": results, "statuses": statuses });
+    if interrupted {
+        let done = batches + failed_batches;
+        eprintln!("interrupted after {done} of {total} batches; writing partial results");
+        return Ok(ContentsOutcome::Interrupted(payload));
+    }
+    Ok(ContentsOutcome::Merged {
+        payload,
+        failed_batches,
+    })
+}
+
+impl Stream<'_> {
+    fn line(&mut self, result: &Value) -> Result<()> {
+        match self.fields {
+            [] => self.write(result),
+            fields => self.write(&project_fields(std::slice::from_ref(result), fields)[0]),
+        }
+    }
+
+    /// Writes one NDJSON line and flushes it so readers see it right away.
+    fn write(&mut self, line: &Value) -> Result<()> {
+        writeln!(self.out, "{line}")
+            .and_then(|()| self.out.flush())
+            .context("write streamed result")
+    }
+}
+
+pub(crate) struct SearchBatch<'a> {
+    pub(crate) concurrency: usize,
+    pub(crate) project: Option<&'a [&'a str]>,
+    pub(crate) min_score: Option<f64>,
+    /// Stop sending queries once this many results were collected.
+    pub(crate) max_results: Option<usize>,
+}
+
+pub(crate) struct BatchOutcome {
+    /// `{"results", ...}` ready for the output pipeline.
+    pub(crate) payload: Value,
+    /// Requests that failed and were left out of `payload`.
+    pub(crate) failed: usize,
+}
+
+/// How `render_lines` prints a batch payload.
+#[derive(Clone, Copy)]
+pub(crate) enum Lines {
+    /// `--queries-file`: grouped by query line, optionally wrapped.
+    Queries { wrap: bool },
+    /// `--bodies-file`: one `{"line", "response"}` record per line.
+    Bodies,
+}
+
+/// Runs one search per query line. The payload holds every result tagged
+/// with the `query` that found it, plus the `queries` lines that succeeded.
+/// Repeated lines are only sent once, and no further query is sent once
+/// `max_results` results are in. A failed query is reported on stderr and
+/// left out, so the others still make it into the payload; it is an error
+/// only when no query succeeds.
+pub(crate) async fn searches(
+    api: &Api,
+    body: Map<String, Value>,
+    queries: &[String],
+    opts: &SearchBatch<'_>,
+) -> Result<BatchOutcome> {
+    // Lines that build the same request body are sent once.
+    let mut seen = HashSet::new();
+    let mut unique: Vec<(&str, Map<String, Value>)> = Vec::new();
+    for query in queries {
+        let mut body = body.clone();
+        body.insert("query".to_string(), Value::String(query.clone()));
+        let key = canonicalize(&Value::Object(body.clone())).to_string();
+        if seen.insert(key) {
+            unique.push((query, body));
+        }
+    }
+    let collapsed = queries.len() - unique.len();
+    if collapsed > 0 && api.verbose >= 1 {
+        eprintln!("collapsed {collapsed} duplicate queries");
+    }
+
+    let requests = unique.into_iter().map(|(query, body)| async move {
+        (query, exa_post(api, "/search", Value::Object(body)).await)
+    });
+    let total = requests.len();
+    let mut responses = futures::stream::iter(requests).buffered(opts.concurrency);
+
+    let max = opts.max_results.unwrap_or(usize::MAX);
+    let (mut results, mut answered, mut failed, mut last_err) =
+        (Vec::new(), Vec::new(), Vec::new(), None);
+    while let Some((query, response)) = responses.next().await {
+        let mut response = match response {
+            Ok(response) => response,
+            Err(err) => {
+                eprintln!("query {query:?} failed: {err:#}");
+                failed.push(query);
+                last_err = Some(err);
+                continue;
+            }
+        };
+        answered.push(query);
+        let mut found = take_array(&mut response, "results");
+        if let Some(min) = opts.min_score {
+            drop_below_score(&mut found, min);
+        }
+        for mut result in found {
+            if let Some(fields) = opts.project {
+                project_result(&mut result, fields);
+            }
+            if let Value::Object(result) = &mut result {
+                result.insert("query".to_string(), Value::String(query.to_string()));
+            }
+            results.push(result);
+        }
+        if results.len() >= max {
+            // Queries not yet started are never sent.
+            let skipped = total - answered.len() - failed.len();
+            if skipped > 0 && api.verbose >= 1 {
+                eprintln!("--max-results {max} reached; skipped {skipped} queries");
+            }
+            break;
+        }
+    }
+    report_breaker(api, total);
+    if let Some(err) = last_err
+        && answered.is_empty()
+    {
+        return Err(err.context(format!("no query succeeded ({} failed)", failed.len())));
+    }
+    let answered: Vec<&String> = queries
+        .iter()
+        .filter(|query| answered.contains(&query.as_str()))
+        .collect();
+    Ok(BatchOutcome {
+        payload: json!({ "results": results, "queries": answered }),
+        failed: failed.len(),
+    })
+}
+
+/// Renders a batch payload as NDJSON, projecting each line to `fields`.
+/// Query batches print one group per query line, so a repeated line gets
+/// its results again. Wrapped, each line is `{"query", "result"}` and a
+/// query left without results still gets a `"result": null` marker;
+/// otherwise lines are the bare results.
+pub(crate) fn render_lines(payload: &Value, lines: Lines, fields: &[String]) -> String {
+    let results = payload
+        .get("results")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let project = |result: Value| match fields {
+        [] => result,
+        _ => project_fields(std::slice::from_ref(&result), fields).remove(0),
+    };
+    let Lines::Queries { wrap } = lines else {
+        let records: Vec<String> = results
+            .iter()
+            .map(|record| project(record.clone()).to_string())
+            .collect();
+        return records.join("\n");
+    };
+    let queries = payload
+        .get("queries")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let mut lines = Vec::new();
+    for query in queries {
+        let mut group = results
+            .iter()
+            .filter(|result| result.get("query") == Some(query))
+            .map(|result| {
+                let mut result = result.clone();
+                if let Value::Object(map) = &mut result {
+                    map.remove("query");
+                }
+                project(result)
+            })
+            .peekable();
+        if wrap && group.peek().is_none() {
+            lines.push(json!({ "query": query, "result": null }).to_string());
+        }
+        for result in group {
+            let line = if wrap {
+                json!({ "query": query, "result": result })
+            } else {
+                result
+            };
+            lines.push(line.to_string());
+        }
+    }
+    lines.join("\n")
+}
+
+/// Reads a `--bodies-file`: one JSON object per line, paired with its
+/// 1-based line number. Blank lines are skipped.
+pub(crate) fn read_bodies(path: &Path) -> Result<Vec<(usize, Map<String, Value>)>> {
+    let raw =
+        fs::read_to_string(path).with_context(|| format!("read bodies file {}", path.display()))?;
+    let mut bodies = Vec::new();
+    for (idx, line) in raw.lines().enumerate() {
+        let line_no = idx + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: Value = serde_json::from_str(line)
+            .with_context(|| format!("parse {} line {line_no}", path.display()))?;
+        let Value::Object(body) = value else {
+            return Err(anyhow!(
+                "{} line {line_no}: body must be a JSON object",
+                path.display()
+            ));
+        };
+        bodies.push((line_no, body));
+    }
+    Ok(bodies)
+}
+
+/// Sends each body to `endpoint`, collecting `{"line", "response"}`
+/// records in input order. A failed line is reported on stderr and left
+/// out; it is an error only when every line fails.
+pub(crate) async fn bodies(
+    api: &Api,
+    endpoint: &str,
+    bodies: Vec<(usize, Map<String, Value>)>,
+    concurrency: usize,
+) -> Result<BatchOutcome> {
+    let total = bodies.len();
+    let requests = bodies.into_iter().map(|(line, mut body)| {
+        if endpoint == "/answer" {
+            // Streamed events are not one JSON response; stream only on request.
+            body.entry("stream").or_insert(Value::Bool(false));
+        }
+        async move { (line, exa_post(api, endpoint, Value::Object(body)).await) }
+    });
+    let responses = futures::stream::iter(requests)
+        .buffered(concurrency)
+        .collect::<Vec<_>>()
+        .await;
+    report_breaker(api, total);
+
+    let (mut records, mut failed, mut last_err) = (Vec::new(), 0, None);
+    for (line, response) in responses {
+        match response {
+            Ok(response) => records.push(json!({ "line": line, "response": response })),
+            Err(err) => {
+                eprintln!("bodies file line {line} failed: {err:#}");
+                failed += 1;
+                last_err = Some(err);
+            }
+        }
+    }
+    if let Some(err) = last_err
+        && failed == total
+    {
+        return Err(err.context(format!("no body succeeded ({failed} failed)")));
+    }
+    Ok(BatchOutcome {
+        payload: json!({ "results": records }),
+        failed,
+    })
+}
+
+fn report_breaker(api: &Api, total: usize) {
+    let skipped = api.breaker.skipped();
+    if skipped > 0 {
+        eprintln!(
+            "circuit breaker opened after {} consecutive failures: skipped {skipped} of {total} requests",
+            api.breaker.threshold()
+        );
+    }
+}
+
+fn chunk_targets(mut body: Map<String, Value>, batch_size: usize) -> Vec<Map<String, Value>> {
+    let mut targets = Vec::new();
+    for key in ["urls", "ids"] {
+        if let Some(Value::Array(items)) = body.remove(key) {
+            targets.extend(items.into_iter().map(|item| (key, item)));
+        }
+    }
+    targets
+        .chunks(batch_size.max(1))
+        .map(|chunk| {
+            let mut part = body.clone();
+            for (key, item) in chunk {
+                part.entry(key.to_string())
+                    .or_insert_with(|| Value::Array(Vec::new()))
+                    .as_array_mut()
+                    .expect("target list is an array")
+                    .push(item.clone());
+            }
+            part
+        })
+        .collect()
+}
+
+fn take_array(response: &mut Value, key: &str) -> Vec<Value> {
+    match response.get_mut(key).map(Value::take) {
+        Some(Value::Array(items)) => items,
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{
+        test_api,
+        testserver::{serve, serve_recorded},
+    };
+
+    /// A `Write` the test can read back after the batch owns it.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn contents_opts<'a>(stream: Option<Stream<'a>>) -> ContentsBatch<'a> {
+        ContentsBatch {
+            batch_size: 1,
+            concurrency: 1,
+            stream,
+            ordered: true,
+            max_results: None,
+            project: None,
+            partial_on_interrupt: false,
+        }
+    }
+
+    const PAGE_A: &str = r#"{"results":[{"url":"a"}],"statuses":[{"id":"a","status":"success"}]}"#;
+    const PAGE_C: &str = r#"{"results":[{"url":"c"}],"statuses":[{"id":"c","status":"success"}]}"#;
+    const DOWN: &str = r#"{"error":"unavailable"}"#;
+
+    fn three_urls() -> Map<String, Value> {
+        let Value::Object(body) = json!({ "urls": ["a", "b", "c"] }) else {
+            unreachable!()
+        };
+        body
+    }
+
+    #[tokio::test]
+    async fn streamed_contents_skip_a_failed_batch_and_still_summarize() {
+        let base = serve(vec![
+            (Duration::ZERO, 200, PAGE_A),
+            (Duration::ZERO, 503, DOWN),
+            (Duration::ZERO, 200, PAGE_C),
+        ]);
+        let api = test_api(&base, 0);
+        let out = Shared::default();
+        let stream = Stream {
+            out: Box::new(out.clone()),
+            fields: &[],
+        };
+        let outcome = contents(&api, three_urls(), contents_opts(Some(stream)))
+            .await
+            .expect("two batches succeed");
+        let ContentsOutcome::Streamed { failed_batches, .. } = outcome else {
+            panic!("not streamed");
+        };
+        assert_eq!(failed_batches, 1);
+        let written = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<Value> = written
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines[..2], [json!({ "url": "a" }), json!({ "url": "c" })]);
+        assert_eq!(lines[2]["summary"]["batches"], 2);
+        assert_eq!(lines[2]["summary"]["failed"], 1);
+        assert_eq!(lines[2]["summary"]["failed_batches"], 1);
+    }
+
+    #[tokio::test]
+    async fn searches_stop_sending_once_max_results_are_in() {
+        const TWO: &str = r#"{"results":[{"url":"a"},{"url":"b"}]}"#;
+        let (base, requests) = serve_recorded(vec![(Duration::ZERO, 200, TWO); 3]);
+        let api = test_api(&base, 0);
+        let queries: Vec<String> = ["q1", "q2", "q3"].map(str::to_string).to_vec();
+        let opts = SearchBatch {
+            concurrency: 1,
+            project: None,
+            min_score: None,
+            max_results: Some(3),
+        };
+        let outcome = searches(&api, Map::new(), &queries, &opts)
+            .await
+            .expect("searches succeed");
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert_eq!(outcome.payload["queries"], json!(["q1", "q2"]));
+        assert_eq!(outcome.payload["results"].as_array().unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn searches_send_a_repeated_line_once() {
+        const ONE: &str = r#"{"results":[{"url":"a"}]}"#;
+        let (base, requests) = serve_recorded(vec![(Duration::ZERO, 200, ONE); 3]);
+        let api = test_api(&base, 0);
+        let queries: Vec<String> = ["robots", "drones", "robots"].map(str::to_string).to_vec();
+        let opts = SearchBatch {
+            concurrency: 2,
+            project: None,
+            min_score: None,
+            max_results: None,
+        };
+        let outcome = searches(&api, Map::new(), &queries, &opts)
+            .await
+            .expect("searches succeed");
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert_eq!(
+            outcome.payload["queries"],
+            json!(["robots", "drones", "robots"])
+        );
+    }
+
+    #[tokio::test]
+    async fn merged_contents_list_a_failed_batch_per_url() {
+        let base = serve(vec![
+            (Duration::ZERO, 200, PAGE_A),
+            (Duration::ZERO, 503, DOWN),
+            (Duration::ZERO, 200, PAGE_C),
+        ]);
+        let api = test_api(&base, 0);
+        let outcome = contents(&api, three_urls(), contents_opts(None))
+            .await
+            .expect("two batches succeed");
+        let ContentsOutcome::Merged {
+            payload,
+            failed_batches,
+        } = outcome
+        else {
+            panic!("not merged");
+        };
+        assert_eq!(failed_batches, 1);
+        assert_eq!(payload["results"], json!([{ "url": "a" }, { "url": "c" }]));
+        let statuses = payload["statuses"].as_array().unwrap();
+        let failed: Vec<&Value> = statuses
+            .iter()
+            .filter(|status| status["status"] == "error")
+            .map(|status| &status["id"])
+            .collect();
+        assert_eq!(failed, [&json!("b")]);
+    }
+
+    #[tokio::test]
+    async fn contents_fail_when_every_batch_fails() {
+        let base = serve(vec![(Duration::ZERO, 503, DOWN); 3]);
+        let api = test_api(&base, 0);
+        let err = contents(&api, three_urls(), contents_opts(None))
+            .await
+            .err()
+            .expect("nothing succeeded");
+        assert!(
+            err.to_string().contains("no contents batch succeeded"),
+            "{err}"
+        );
+    }
+
+    fn queries_payload() -> Value {
+        json!({
+            "results": [
+                { "url": "https://a.com", "title": "A", "query": "robots" },
+                { "url": "https://b.com", "title": "B", "query": "robots" },
+                { "url": "https://c.com", "title": "C", "query": "drones" },
+            ],
+            "queries": ["robots", "nothing", "drones", "robots"],
+        })
+    }
+
+    fn lines(text: &str) -> Vec<Value> {
+        text.lines()
+            .map(|line| serde_json::from_str(line).expect("each line is JSON"))
+            .collect()
+    }
+
+    #[test]
+    fn wrapped_lines_mark_queries_without_results() {
+        let out = render_lines(&queries_payload(), Lines::Queries { wrap: true }, &[]);
+        assert_eq!(
+            lines(&out),
+            [
+                json!({ "query": "robots", "result": { "url": "https://a.com", "title": "A" } }),
+                json!({ "query": "robots", "result": { "url": "https://b.com", "title": "B" } }),
+                json!({ "query": "nothing", "result": null }),
+                json!({ "query": "drones", "result": { "url": "https://c.com", "title": "C" } }),
+                json!({ "query": "robots", "result": { "url": "https://a.com", "title": "A" } }),
+                json!({ "query": "robots", "result": { "url": "https://b.com", "title": "B" } }),
+            ]
+        );
+    }
+
+    #[test]
+    fn wrapped_lines_mark_every_query_when_nothing_matched() {
+        let payload = json!({ "results": [], "queries": ["a", "b"] });
+        let out = render_lines(&payload, Lines::Queries { wrap: true }, &[]);
+        assert_eq!(
+            lines(&out),
+            [
+                json!({ "query": "a", "result": null }),
+                json!({ "query": "b", "result": null }),
+            ]
+        );
+    }
+
+    #[test]
+    fn bare_lines_drop_the_query_tag_and_skip_empty_queries() {
+        let out = render_lines(&queries_payload(), Lines::Queries { wrap: false }, &[]);
+        let urls: Vec<Value> = lines(&out).iter().map(|line| line["url"].clone()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://a.com",
+                "https://b.com",
+                "https://c.com",
+                "https://a.com",
+                "https://b.com"
+            ]
+        );
+        assert!(lines(&out).iter().all(|line| line.get("query").is_none()));
+    }
+
+    #[test]
+    fn lines_are_projected_to_fields() {
+        let fields = ["url".to_string()];
+        let out = render_lines(&queries_payload(), Lines::Queries { wrap: true }, &fields);
+        assert_eq!(
+            lines(&out)[0],
+            json!({ "query": "robots", "result": { "url": "https://a.com" } })
+        );
+    }
+
+    #[test]
+    fn body_records_print_one_per_line() {
+        let payload = json!({ "results": [
+            { "line": 1, "response": { "answer": "x" } },
+            { "line": 3, "response": { "answer": "y" } },
+        ]});
+        let out = render_lines(&payload, Lines::Bodies, &["line".to_string()]);
+        assert_eq!(out, "{\"line\":1}\n{\"line\":3}");
+    }
+
+    #[test]
+    fn chunk_targets_splits_urls_then_ids() {
+        let body = json!({ "urls": ["u1", "u2", "u3"], "ids": ["i1"], "text": true });
+        let Value::Object(body) = body else {
+            unreachable!()
+        };
+        let chunks: Vec<Value> = chunk_targets(body, 2)
+            .into_iter()
+            .map(Value::Object)
+            .collect();
+        assert_eq!(
+            chunks,
+            [
+                json!({ "urls": ["u1", "u2"], "text": true }),
+                json!({ "urls": ["u3"], "ids": ["i1"], "text": true }),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_bodies_numbers_lines_and_rejects_non_objects() {
+        let dir = std::env::temp_dir().join(format!("exa-bodies-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let good = dir.join("good.ndjson");
+        fs::write(&good, "{\"query\":\"a\"}\n\n{\"query\":\"b\"}\n").unwrap();
+        let bodies = read_bodies(&good).expect("valid file");
+        let lines: Vec<usize> = bodies.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, [1, 3]);
+        assert_eq!(bodies[1].1["query"], "b");
+
+        let bad = dir.join("bad.ndjson");
+        fs::write(&bad, "{\"query\":\"a\"}\n[1]\n").unwrap();
+        let err = read_bodies(&bad).expect_err("array line");
+        assert!(err.to_string().contains("line 2"), "{err}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Config {
+    pub(crate) api_key: Option<String>,
+    #[serde(default)]
+    pub(crate) api_keys: Vec<String>,
+    pub(crate) api_base: Option<String>,
+    pub(crate) timeout: Option<u64>,
+    pub(crate) answer_model: Option<String>,
+    #[serde(default)]
+    pub(crate) contents: ContentDefaults,
+    pub(crate) default_command: Option<String>,
+    #[serde(default)]
+    pub(crate) profile: BTreeMap<String, Profile>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Profile {
+    pub(crate) api_key: Option<String>,
+    /// Extra keys for `--key-rotation`.
+    #[serde(default)]
+    pub(crate) api_keys: Vec<String>,
+    pub(crate) api_base: Option<String>,
+    pub(crate) timeout: Option<u64>,
+    /// Default `answer --model` when neither the flag nor the body sets one.
+    pub(crate) answer_model: Option<String>,
+    #[serde(default)]
+    pub(crate) contents: ContentDefaults,
+}
+
+/// `[contents]` options added to search, find-similar and contents requests
+/// that do not set them through a flag or the body.
+#[derive(Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ContentDefaults {
+    pub(crate) text: Option<Value>,
+    pub(crate) highlights: Option<Value>,
+    pub(crate) summary: Option<Value>,
+    pub(crate) livecrawl: Option<String>,
+}
+
+impl ContentDefaults {
+    /// The options that are set, keyed by their body field name.
+    pub(crate) fn fields(&self) -> Map<String, Value> {
+        [
+            ("text", self.text.clone()),
+            ("highlights", self.highlights.clone()),
+            ("summary", self.summary.clone()),
+            ("livecrawl", self.livecrawl.clone().map(Value::String)),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.to_string(), value?)))
+        .collect()
+    }
+
+    fn or(self, fallback: &Self) -> Self {
+        Self {
+            text: self.text.or_else(|| fallback.text.clone()),
+            highlights: self.highlights.or_else(|| fallback.highlights.clone()),
+            summary: self.summary.or_else(|| fallback.summary.clone()),
+            livecrawl: self.livecrawl.or_else(|| fallback.livecrawl.clone()),
+        }
+    }
+}
+
+impl Config {
+    /// Values for the named profile (or `default`), falling back to top-level keys.
+    /// Only an explicitly requested profile has to exist.
+    pub(crate) fn profile(&self, name: Option<&str>) -> Result<Profile> {
+        let selected = match name {
+            Some(name) => self.profile.get(name).cloned().ok_or_else(|| {
+                let known: Vec<&str> = self.profile.keys().map(String::as_str).collect();
+                anyhow!("unknown profile {name:?} (known: {})", known.join(", "))
+            })?,
+            None => self.profile.get("default").cloned().unwrap_or_default(),
+        };
+        Ok(Profile {
+            api_key: selected.api_key.or_else(|| self.api_key.clone()),
+            api_keys: if selected.api_keys.is_empty() {
+                self.api_keys.clone()
+            } else {
+                selected.api_keys
+            },
+            api_base: selected.api_base.or_else(|| self.api_base.clone()),
+            timeout: selected.timeout.or(self.timeout),
+            answer_model: selected.answer_model.or_else(|| self.answer_model.clone()),
+            contents: selected.contents.or(&self.contents),
+        })
+    }
+}
+
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir).join("exa"));
+    }
+    std::env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(|home| PathBuf::from(home).join(".config").join("exa"))
+}
+
+pub(crate) fn cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir).join("exa"));
+    }
+    std::env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(|home| PathBuf::from(home).join(".cache").join("exa"))
+}
+
+pub(crate) fn config_path() -> Option<PathBuf> {
+    match std::env::var_os("EXA_CONFIG").filter(|path| !path.is_empty()) {
+        Some(path) => Some(PathBuf::from(path)),
+        None => config_dir().map(|dir| dir.join("config.toml")),
+    }
+}
+
+pub(crate) fn load() -> Result<Config> {
+    let Some(path) = config_path() else {
+        return Ok(Config::default());
+    };
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+    let raw =
+        fs::read_to_string(&path).with_context(|| format!("read config {}", path.display()))?;
+    toml::from_str(&raw).map_err(|err| anyhow!("parse config {}: {err}", path.display()))
+}
@@ -0,0 +1,90 @@
+use anyhow::{Result, anyhow};
+use chrono::{
+    DateTime, Local, SecondsFormat, TimeDelta, Utc,
+    format::{Item, StrftimeItems},
+};
+use serde_json::Value;
+
+pub(crate) const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+const DATE_KEYS: [&str; 8] = [
+    "publishedDate",
+    "crawlDate",
+    "startPublishedDate",
+    "endPublishedDate",
+    "startCrawlDate",
+    "endCrawlDate",
+    "createdAt",
+    "finishedAt",
+];
+
+pub(crate) fn parse_duration(raw: &str) -> Result<TimeDelta> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Err(anyhow!("empty duration"));
+    }
+    let mut total = TimeDelta::zero();
+    let mut digits = String::new();
+    for c in raw.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let amount: i64 = digits
+            .parse()
+            .map_err(|_| anyhow!("invalid duration {raw:?}: expected a number before {c:?}"))?;
+        digits.clear();
+        let part = match c {
+            's' => TimeDelta::try_seconds(amount),
+            'm' => TimeDelta::try_minutes(amount),
+            'h' => TimeDelta::try_hours(amount),
+            'd' => TimeDelta::try_days(amount),
+            'w' => TimeDelta::try_weeks(amount),
+            _ => {
+                return Err(anyhow!(
+                    "invalid duration {raw:?}: unknown unit {c:?} (use s, m, h, d, w)"
+                ));
+            }
+        };
+        total = part
+            .and_then(|part| total.checked_add(&part))
+            .ok_or_else(|| anyhow!("duration {raw:?} is too large"))?;
+    }
+    if !digits.is_empty() {
+        return Err(anyhow!(
+            "invalid duration {raw:?}: missing unit after {digits}"
+        ));
+    }
+    Ok(total)
+}
+
+pub(crate) fn ago(duration: TimeDelta) -> String {
+    (Utc::now() - duration).to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+pub(crate) fn validate_date_format(format: &str) -> Result<()> {
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return Err(anyhow!("invalid --date-format {format:?}"));
+    }
+    Ok(())
+}
+
+pub(crate) fn prettify_dates(value: &mut Value, format: &str) {
+    match value {
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                if DATE_KEYS.contains(&key.as_str())
+                    && let Value::String(text) = item
+                    && let Ok(parsed) = DateTime::parse_from_rfc3339(text)
+                {
+                    *text = parsed.with_timezone(&Local).format(format).to_string();
+                } else {
+                    prettify_dates(item, format);
+                }
+            }
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| prettify_dates(item, format)),
+        _ => {}
+    }
+}
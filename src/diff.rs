@@ -0,0 +1,138 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+
+use crate::result_list;
+
+/// Fields left out when deciding whether a result changed between runs.
+const VOLATILE_FIELDS: [&str; 1] = ["score"];
+
+pub(crate) fn load_results(path: &Path) -> Result<Vec<Value>> {
+    let raw =
+        fs::read_to_string(path).with_context(|| format!("read results {}", path.display()))?;
+    let payload: Value = serde_json::from_str(&raw)
+        .map_err(|err| anyhow!("parse results {}: {err}", path.display()))?;
+    result_list(&payload)
+        .cloned()
+        .with_context(|| format!("{} has no results array", path.display()))
+}
+
+/// Matches results by `id`, falling back to `url`, and lists what was added,
+/// removed or changed from `old` to `new`.
+pub(crate) fn diff(old: &[Value], new: &[Value]) -> Value {
+    let old_by_key: HashMap<String, &Value> =
+        old.iter().filter_map(|r| key(r).map(|k| (k, r))).collect();
+    let new_keys: Vec<Option<String>> = new.iter().map(key).collect();
+
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    for (result, key) in new.iter().zip(&new_keys) {
+        match key.as_ref().and_then(|key| old_by_key.get(key)) {
+            None => added.push(brief(result)),
+            Some(before) => {
+                let fields = changed_fields(before, result);
+                if !fields.is_empty() {
+                    let mut entry = brief(result);
+                    entry["fields"] = json!(fields);
+                    changed.push(entry);
+                }
+            }
+        }
+    }
+    let seen: HashSet<&String> = new_keys.iter().flatten().collect();
+    let removed: Vec<Value> = old
+        .iter()
+        .filter(|result| key(result).is_none_or(|key| !seen.contains(&key)))
+        .map(brief)
+        .collect();
+    json!({ "added": added, "removed": removed, "changed": changed })
+}
+
+/// Adds `new` results to `old`, matched like `diff`: a result already present
+/// is replaced in place by its newer copy, others are appended. Returns the
+/// merged list and how many results were added.
+pub(crate) fn union(mut old: Vec<Value>, new: &[Value]) -> (Vec<Value>, usize) {
+    let mut index: HashMap<String, usize> = old
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, result)| key(result).map(|key| (key, idx)))
+        .collect();
+    let mut added = 0;
+    for result in new {
+        match key(result).and_then(|key| index.get(&key).copied()) {
+            Some(idx) => old[idx] = result.clone(),
+            None => {
+                if let Some(key) = key(result) {
+                    index.insert(key, old.len());
+                }
+                old.push(result.clone());
+                added += 1;
+            }
+        }
+    }
+    (old, added)
+}
+
+pub(crate) fn summary(diff: &Value) -> String {
+    let list = |name: &str| diff[name].as_array().map(Vec::as_slice).unwrap_or_default();
+    let (added, removed, changed) = (list("added"), list("removed"), list("changed"));
+    let mut lines = vec![format!(
+        "{} new, {} removed, {} changed",
+        added.len(),
+        removed.len(),
+        changed.len()
+    )];
+    for (mark, entries) in [("+", added), ("-", removed), ("~", changed)] {
+        for entry in entries {
+            let label = entry
+                .get("url")
+                .or_else(|| entry.get("id"))
+                .and_then(Value::as_str)
+                .unwrap_or("?");
+            match entry.get("title").and_then(Value::as_str) {
+                Some(title) => lines.push(format!("{mark} {label}  {title}")),
+                None => lines.push(format!("{mark} {label}")),
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+fn key(result: &Value) -> Option<String> {
+    result
+        .get("id")
+        .or_else(|| result.get("url"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+fn brief(result: &Value) -> Value {
+    let mut out = json!({});
+    for field in ["id", "url", "title"] {
+        if let Some(value) = result.get(field) {
+            out[field] = value.clone();
+        }
+    }
+    out
+}
+
+fn changed_fields(before: &Value, after: &Value) -> Vec<String> {
+    let (Some(before), Some(after)) = (before.as_object(), after.as_object()) else {
+        return Vec::new();
+    };
+    let mut fields: Vec<String> = before
+        .keys()
+        .chain(after.keys())
+        .filter(|field| !VOLATILE_FIELDS.contains(&field.as_str()))
+        .filter(|field| before.get(*field) != after.get(*field))
+        .cloned()
+        .collect();
+    fields.sort();
+    fields.dedup();
+    fields
+}
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use reqwest::Method;
+
+use crate::{
+    Cli, build_client,
+    config::{self, Config},
+    env::Env,
+    parse_api_base, resolve_api_base, resolve_api_key,
+};
+
+struct Checks {
+    failed: bool,
+}
+
+impl Checks {
+    fn pass(&self, name: &str, detail: &str) {
+        println!("[pass] {name}: {detail}");
+    }
+
+    fn fail(&mut self, name: &str, detail: &str, hint: &str) {
+        self.failed = true;
+        println!("[fail] {name}: {detail}");
+        println!("       hint: {hint}");
+    }
+
+    fn skip(&self, name: &str, detail: &str) {
+        println!("[skip] {name}: {detail}");
+    }
+}
+
+/// Runs each setup check in turn and exits 1 if any critical one failed.
+pub(crate) async fn run(cli: &Cli, config: Result<Config>) -> Result<i32> {
+    let mut checks = Checks { failed: false };
+
+    let config_path = config::config_path();
+    let config = match (config, &config_path) {
+        (Ok(config), Some(path)) if path.exists() => {
+            checks.pass("config", &format!("parsed {}", path.display()));
+            config
+        }
+        (Ok(config), _) => {
+            checks.skip("config", "no config file found");
+            config
+        }
+        (Err(err), _) => {
+            checks.fail(
+                "config",
+                &format!("{err:#}"),
+                "fix the TOML syntax or unknown keys, or point EXA_CONFIG elsewhere",
+            );
+            Config::default()
+        }
+    };
+
+    let env = match Env::load(cli.env_file.as_deref(), cli.no_env) {
+        Ok(env) => env,
+        Err(err) => {
+            checks.fail(
+                "env file",
+                &format!("{err:#}"),
+                "check the --env-file path and syntax",
+            );
+            Env::empty(cli.no_env)
+        }
+    };
+    let profile_name = cli.profile.clone().or_else(|| env.var("EXA_PROFILE"));
+    let profile = match config.profile(profile_name.as_deref()) {
+        Ok(profile) => {
+            if let Some(name) = &profile_name {
+                checks.pass("profile", &format!("using [profile.{name}]"));
+            }
+            profile
+        }
+        Err(err) => {
+            checks.fail(
+                "profile",
+                &format!("{err:#}"),
+                "add the [profile.NAME] section or drop --profile/EXA_PROFILE",
+            );
+            config.profile(None).unwrap_or_default()
+        }
+    };
+
+    match resolve_api_key(cli, &env, &profile) {
+        Some((key, _)) if key.trim().is_empty() => checks.fail(
+            "api key",
+            "resolved key is empty",
+            "set EXA_API_KEY to a key from https://dashboard.exa.ai",
+        ),
+        Some((_, source)) => checks.pass("api key", &format!("found via {source}")),
+        None => checks.fail(
+            "api key",
+            "no key from --api-key, EXA_API_KEY or the config file",
+            "export EXA_API_KEY=... or add api_key to the config file",
+        ),
+    }
+
+    let (api_base, source) = resolve_api_base(cli, &env, &profile);
+    let api_base = api_base.trim_end_matches('/');
+    match parse_api_base(api_base) {
+        Ok(url) => {
+            let timeout = cli.timeout.or(profile.timeout).unwrap_or(30);
+            let reached = match build_client(cli, timeout) {
+                Ok(client) => client
+                    .request(Method::GET, url)
+                    .send()
+                    .await
+                    .map_err(|err| format!("{:#}", anyhow::Error::from(err))),
+                Err(err) => Err(format!("{err:#}")),
+            };
+            match reached {
+                Ok(response) => checks.pass(
+                    "api base",
+                    &format!("{api_base} ({source}) answered {}", response.status()),
+                ),
+                Err(err) => checks.fail(
+                    "api base",
+                    &format!("{api_base} ({source}) unreachable: {err}"),
+                    "check network/proxy settings, --ca-cert, or the --api-base value",
+                ),
+            }
+        }
+        Err(err) => checks.fail(
+            "api base",
+            &format!("{err:#}"),
+            "use an http(s) URL such as https://api.exa.ai",
+        ),
+    }
+
+    // Keys come from flags, env and config only; there is no keyring to probe.
+    checks.skip("keyring", "keyring support not built in");
+
+    Ok(if checks.failed { 1 } else { 0 })
+}
@@ -0,0 +1,110 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use reqwest::{Request, StatusCode, Version, header::HeaderMap};
+
+use crate::output::shell_quote;
+
+const REDACTED_HEADERS: [&str; 2] = ["x-api-key", "authorization"];
+
+pub(crate) struct Dumper {
+    dir: PathBuf,
+    seq: AtomicUsize,
+}
+
+impl Dumper {
+    pub(crate) fn new(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("create dump dir {}", dir.display()))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            seq: AtomicUsize::new(0),
+        })
+    }
+
+    pub(crate) fn request(&self, request: &Request, body: Option<&[u8]>) -> Result<String> {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        let endpoint: String = request
+            .url()
+            .path()
+            .trim_matches('/')
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let stem = format!(
+            "{}-{seq:03}-{}-{endpoint}",
+            Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+            request.method().as_str().to_ascii_lowercase()
+        );
+        let mut out = format!("{} {}\n", request.method(), request.url());
+        write_headers(&mut out, request.headers());
+        out.push('\n');
+        let mut raw = out.into_bytes();
+        raw.extend_from_slice(body.unwrap_or_default());
+        self.write(&format!("{stem}.request.txt"), &raw)?;
+        Ok(stem)
+    }
+
+    pub(crate) fn response(
+        &self,
+        stem: &str,
+        status: StatusCode,
+        version: Version,
+        headers: &HeaderMap,
+        body: &str,
+    ) -> Result<()> {
+        let mut out = format!("{version:?} {status}\n");
+        write_headers(&mut out, headers);
+        out.push('\n');
+        out.push_str(body);
+        self.write(&format!("{stem}.response.txt"), out.as_bytes())
+    }
+
+    fn write(&self, name: &str, raw: &[u8]) -> Result<()> {
+        let path = self.dir.join(name);
+        fs::write(&path, raw).with_context(|| format!("write dump file {}", path.display()))
+    }
+}
+
+fn write_headers(out: &mut String, headers: &HeaderMap) {
+    for (name, value) in headers {
+        let value = if REDACTED_HEADERS.contains(&name.as_str()) {
+            "[redacted]"
+        } else {
+            value.to_str().unwrap_or("[binary]")
+        };
+        let _ = writeln!(out, "{name}: {value}");
+    }
+}
+
+/// A copy-pasteable curl equivalent of `request`. The API key is replaced with
+/// a `$EXA_API_KEY` reference so the command is safe to share.
+pub(crate) fn curl_command(request: &Request, body: Option<&[u8]>) -> String {
+    let mut parts = vec!["curl".to_string()];
+    if request.method() != reqwest::Method::GET {
+        parts.push(format!("-X {}", request.method()));
+    }
+    parts.push(shell_quote(request.url().as_str()));
+    for (name, value) in request.headers() {
+        let header = if name.as_str() == "x-api-key" {
+            format!("\"{name}: $EXA_API_KEY\"")
+        } else if REDACTED_HEADERS.contains(&name.as_str()) {
+            shell_quote(&format!("{name}: [redacted]"))
+        } else {
+            shell_quote(&format!("{name}: {}", value.to_str().unwrap_or("[binary]")))
+        };
+        parts.push(format!("-H {header}"));
+    }
+    if let Some(body) = body {
+        parts.push(format!(
+            "--data-raw {}",
+            shell_quote(&String::from_utf8_lossy(body))
+        ));
+    }
+    parts.join(" \\\n  ")
+}
@@ -0,0 +1,93 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+
+#[derive(Default)]
+pub(crate) struct Env {
+    file: HashMap<String, String>,
+    /// Set by `--no-env`: only the `--env-file` values are consulted.
+    ignore_process: bool,
+}
+
+impl Env {
+    /// No env file; process variables unless `ignore_process`.
+    pub(crate) fn empty(ignore_process: bool) -> Self {
+        Self {
+            ignore_process,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn load(env_file: Option<&Path>, ignore_process: bool) -> Result<Self> {
+        let Some(path) = env_file else {
+            return Ok(Self::empty(ignore_process));
+        };
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("read env file {}", path.display()))?;
+        let file = parse_dotenv(&raw)
+            .map_err(|err| anyhow!("parse env file {}: {err}", path.display()))?;
+        Ok(Self {
+            file,
+            ignore_process,
+        })
+    }
+
+    pub(crate) fn var(&self, key: &str) -> Option<String> {
+        let process = if self.ignore_process {
+            None
+        } else {
+            std::env::var(key).ok()
+        };
+        process.or_else(|| self.file.get(key).cloned())
+    }
+}
+
+fn parse_dotenv(raw: &str) -> Result<HashMap<String, String>> {
+    let mut vars = HashMap::new();
+    for (idx, line) in raw.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow!("line {}: expected KEY=VALUE", idx + 1))?;
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(anyhow!("line {}: invalid key {key:?}", idx + 1));
+        }
+        let value = parse_value(value.trim()).map_err(|err| anyhow!("line {}: {err}", idx + 1))?;
+        vars.insert(key.to_string(), value);
+    }
+    Ok(vars)
+}
+
+fn parse_value(raw: &str) -> Result<String> {
+    if let Some(rest) = raw.strip_prefix('\'') {
+        let end = rest.find('\'').context("unterminated single quote")?;
+        return Ok(rest[..end].to_string());
+    }
+    if let Some(rest) = raw.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Ok(value),
+                '\\' => match chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(other) => value.push(other),
+                    None => break,
+                },
+                _ => value.push(c),
+            }
+        }
+        return Err(anyhow!("unterminated double quote"));
+    }
+    let value = match raw.find(" #") {
+        Some(idx) => &raw[..idx],
+        None => raw,
+    };
+    Ok(value.trim_end().to_string())
+}
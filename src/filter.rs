@@ -0,0 +1,202 @@
+use std::cmp::Ordering;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::Value;
+
+use crate::output::resolve_path;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Gt,
+    Lt,
+    Ge,
+    Le,
+    Eq,
+    Ne,
+    Contains,
+}
+
+/// One `--result-filter` predicate: `FIELD OP VALUE`.
+#[derive(Clone)]
+pub(crate) struct Filter {
+    field: String,
+    op: Op,
+    value: String,
+}
+
+/// Two-character operators first so `>=` is not read as `>`.
+const OPS: [(&str, Op); 6] = [
+    (">=", Op::Ge),
+    ("<=", Op::Le),
+    ("!=", Op::Ne),
+    ("==", Op::Eq),
+    (">", Op::Gt),
+    ("<", Op::Lt),
+];
+
+pub(crate) fn parse(raw: &str) -> Result<Filter, String> {
+    let (field, op, value) = match raw.split_once(" contains ") {
+        Some((field, value)) => (field, Op::Contains, value),
+        None => {
+            let (at, token, op) = OPS
+                .iter()
+                .filter_map(|(token, op)| raw.find(token).map(|at| (at, *token, *op)))
+                .min_by_key(|(at, token, _)| (*at, std::cmp::Reverse(token.len())))
+                .ok_or_else(|| {
+                    format!("{raw:?} has no operator (>, <, >=, <=, ==, !=, contains)")
+                })?;
+            (&raw[..at], op, &raw[at + token.len()..])
+        }
+    };
+    let (field, value) = (field.trim(), value.trim());
+    if field.is_empty() {
+        return Err(format!("{raw:?} has no field before the operator"));
+    }
+    Ok(Filter {
+        field: field.to_string(),
+        op,
+        value: value.to_string(),
+    })
+}
+
+impl Filter {
+    /// Whether `result` passes. A missing or null field only passes `!=`:
+    /// it is not equal to any value, and has nothing to order or search.
+    pub(crate) fn matches(&self, result: &Value) -> bool {
+        let Some(found) = resolve_path(result, &self.field).filter(|value| !value.is_null()) else {
+            return self.op == Op::Ne;
+        };
+        match self.op {
+            Op::Contains => contains(found, &self.value.to_lowercase()),
+            Op::Eq => self.compare(found) == Some(Ordering::Equal),
+            Op::Ne => self.compare(found) != Some(Ordering::Equal),
+            Op::Gt => self.compare(found) == Some(Ordering::Greater),
+            Op::Lt => self.compare(found) == Some(Ordering::Less),
+            Op::Ge => matches!(
+                self.compare(found),
+                Some(Ordering::Greater | Ordering::Equal)
+            ),
+            Op::Le => matches!(self.compare(found), Some(Ordering::Less | Ordering::Equal)),
+        }
+    }
+
+    /// `found` compared to the filter value: numerically for numbers, as
+    /// instants when both sides are dates, else as text.
+    fn compare(&self, found: &Value) -> Option<Ordering> {
+        match found {
+            Value::Number(number) => {
+                let want = self.value.parse::<f64>().ok()?;
+                number.as_f64()?.partial_cmp(&want)
+            }
+            Value::String(text) => match (instant(text), instant(&self.value)) {
+                (Some(found), Some(want)) => Some(found.cmp(&want)),
+                _ => Some(text.as_str().cmp(&self.value)),
+            },
+            Value::Bool(flag) => Some(flag.to_string().cmp(&self.value)),
+            _ => None,
+        }
+    }
+}
+
+/// Case-insensitive substring match; arrays match when any item does.
+fn contains(found: &Value, needle: &str) -> bool {
+    match found {
+        Value::String(text) => text.to_lowercase().contains(needle),
+        Value::Array(items) => items.iter().any(|item| contains(item, needle)),
+        Value::Number(_) | Value::Bool(_) => found.to_string().contains(needle),
+        _ => false,
+    }
+}
+
+/// An RFC 3339 timestamp, or a `YYYY-MM-DD` date taken as midnight UTC.
+fn instant(text: &str) -> Option<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(text) {
+        return Some(at.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn passes(expr: &str, result: &Value) -> bool {
+        parse(expr).expect("valid filter").matches(result)
+    }
+
+    #[test]
+    fn parse_prefers_two_character_operators() {
+        let filter = parse("score >= 0.5").unwrap();
+        assert!(filter.op == Op::Ge);
+        assert_eq!(
+            (filter.field.as_str(), filter.value.as_str()),
+            ("score", "0.5")
+        );
+        assert!(parse("score<=1").unwrap().op == Op::Le);
+        assert!(parse("author!=x").unwrap().op == Op::Ne);
+        assert!(parse("title contains Robot").unwrap().op == Op::Contains);
+    }
+
+    #[test]
+    fn parse_rejects_a_missing_operator_or_field() {
+        assert!(
+            parse("score=1")
+                .err()
+                .is_some_and(|err| err.contains("no operator"))
+        );
+        assert!(
+            parse(">3")
+                .err()
+                .is_some_and(|err| err.contains("no field"))
+        );
+    }
+
+    #[test]
+    fn numbers_compare_numerically() {
+        let result = json!({ "score": 0.5 });
+        assert!(passes("score>0.3", &result));
+        assert!(passes("score<=0.5", &result));
+        assert!(!passes("score<0.5", &result));
+        assert!(passes("score==0.50", &result));
+        assert!(!passes("score>abc", &result));
+    }
+
+    #[test]
+    fn dates_compare_chronologically() {
+        let result = json!({ "publishedDate": "2024-03-01T00:00:00.000Z" });
+        assert!(passes("publishedDate>=2024-01-01", &result));
+        assert!(passes("publishedDate<2024-03-01T00:00:01Z", &result));
+        let older = json!({ "publishedDate": "2023-12-31T23:59:59Z" });
+        assert!(!passes("publishedDate>=2024-01-01", &older));
+    }
+
+    #[test]
+    fn text_and_bools_compare_as_text() {
+        let result = json!({ "title": "Robots", "flag": true });
+        assert!(passes("title==Robots", &result));
+        assert!(!passes("title==robots", &result));
+        assert!(passes("title!=Drones", &result));
+        assert!(passes("flag==true", &result));
+    }
+
+    #[test]
+    fn contains_ignores_case_and_searches_arrays() {
+        let result = json!({ "title": "Humanoid Robots", "extras": { "links": ["https://a.com", "https://b.org"] } });
+        assert!(passes("title contains robot", &result));
+        assert!(passes("extras.links contains B.ORG", &result));
+        assert!(!passes("title contains drone", &result));
+    }
+
+    #[test]
+    fn a_missing_or_null_field_only_passes_not_equal() {
+        for result in [json!({}), json!({ "author": null })] {
+            assert!(!passes("author==Ada", &result));
+            assert!(!passes("author>A", &result));
+            assert!(!passes("author contains a", &result));
+            assert!(passes("author!=Ada", &result));
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use serde_json::Value;
+
+/// One `--explain-errors` rule: matches when the status (if set) is equal
+/// and the error message (if a pattern is set) contains the pattern,
+/// compared lowercase.
+struct Hint {
+    status: Option<u16>,
+    pattern: Option<&'static str>,
+    hint: &'static str,
+}
+
+const fn hint(status: Option<u16>, pattern: Option<&'static str>, hint: &'static str) -> Hint {
+    Hint {
+        status,
+        pattern,
+        hint,
+    }
+}
+
+/// Checked in order; the first match wins.
+const HINTS: [Hint; 9] = [
+    hint(
+        Some(401),
+        None,
+        "your API key appears invalid or revoked; check --api-key, EXA_API_KEY or the config file (--config-print shows which one is used)",
+    ),
+    hint(
+        Some(402),
+        None,
+        "the account is out of credits; top up in the Exa dashboard",
+    ),
+    hint(
+        None,
+        Some("credits"),
+        "the account is out of credits; top up in the Exa dashboard",
+    ),
+    hint(
+        Some(403),
+        None,
+        "this key may not use this endpoint or option; check its permissions in the Exa dashboard",
+    ),
+    hint(
+        None,
+        Some("api key"),
+        "the API did not accept the key; check --api-key, EXA_API_KEY or the config file",
+    ),
+    hint(
+        Some(422),
+        None,
+        "a body field was rejected; `exa schema <endpoint>` lists the known fields and their types",
+    ),
+    hint(
+        Some(400),
+        Some("invalid"),
+        "a body field was rejected; `exa schema <endpoint>` lists the known fields and their types",
+    ),
+    hint(
+        Some(429),
+        None,
+        "rate limited; lower --concurrency, add --delay, or retry with --retries",
+    ),
+    hint(
+        Some(404),
+        None,
+        "the endpoint or id was not found; check --api-base and, for research, the task id",
+    ),
+];
+
+/// Remediation hint for a failed response, or `None` for unrecognized cases.
+pub(crate) fn explain(status: u16, payload: &Value) -> Option<&'static str> {
+    let message = message(payload).to_lowercase();
+    HINTS
+        .iter()
+        .find(|rule| {
+            rule.status.is_none_or(|want| want == status)
+                && rule.pattern.is_none_or(|pattern| message.contains(pattern))
+        })
+        .map(|rule| rule.hint)
+        .or_else(|| {
+            (500..600)
+                .contains(&status)
+                .then_some("the API had a server-side error; retry later or with --retries")
+        })
+}
+
+/// The error text of an API error body: its `error` or `message` field, or
+/// the whole body.
+fn message(payload: &Value) -> String {
+    ["error", "message"]
+        .iter()
+        .find_map(|key| payload.get(key))
+        .unwrap_or(payload)
+        .to_string()
+}
@@ -0,0 +1,332 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Value, json};
+
+use crate::config;
+
+const MAX_ENTRIES: usize = 500;
+const SUMMARY_KEYS: [&str; 4] = ["query", "url", "instructions", "urls"];
+const SUMMARY_CHARS: usize = 80;
+/// Flags whose values never reach the history file.
+const SECRET_FLAGS: [&str; 2] = ["--api-key", "--signing-secret"];
+/// Flags that only feed the request body, dropped on rerun in favour of the
+/// stored body. Each takes one value.
+const BODY_FLAGS: [&str; 18] = [
+    "--body",
+    "--body-file",
+    "--body-format",
+    "--query",
+    "--url",
+    "--instructions",
+    "--category",
+    "--num-results",
+    "--country",
+    "--since",
+    "--until",
+    "--include-domains-file",
+    "--exclude-domains-file",
+    "--extras-links",
+    "--extras-images",
+    "--model",
+    "--system-prompt",
+    "--system-prompt-file",
+];
+/// Body flags that take a list (`--urls a b c`), skipped up to the next flag.
+const BODY_LIST_FLAGS: [&str; 4] = ["--urls", "--ids", "--include-domains", "--exclude-domains"];
+const BODY_SWITCHES: [&str; 3] = ["--body-stdin", "--stream", "--no-stream"];
+/// Batch inputs: a rerun reads the file again, since no one body covers it.
+const BATCH_FLAGS: [&str; 2] = ["--queries-file", "--bodies-file"];
+
+pub(crate) struct Entry<'a> {
+    pub(crate) command: &'a str,
+    pub(crate) args: &'a [String],
+    /// The request body the command assembled, replayed by `exa rerun`.
+    pub(crate) body: Option<Value>,
+    /// The query, URL or name from the flags, shown when there is no body.
+    pub(crate) label: Option<Value>,
+    pub(crate) status: String,
+}
+
+/// Appends one invocation to the history file, keeping the newest
+/// `MAX_ENTRIES`. The API key is stripped from the recorded arguments.
+pub(crate) fn append(entry: Entry<'_>) -> Result<()> {
+    let path = history_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+    }
+    let line = json!({
+        "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        "command": entry.command,
+        "args": strip_secrets(entry.args),
+        "summary": entry.body.as_ref().map(summary).or(entry.label.as_ref().map(summary_text)),
+        "body": entry.body,
+        "status": entry.status,
+    });
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("open history {}", path.display()))?;
+    writeln!(file, "{line}").with_context(|| format!("write history {}", path.display()))?;
+
+    let entries = load()?;
+    if entries.len() > MAX_ENTRIES {
+        let keep: Vec<String> = entries[entries.len() - MAX_ENTRIES..]
+            .iter()
+            .map(Value::to_string)
+            .collect();
+        fs::write(&path, format!("{}\n", keep.join("\n")))
+            .with_context(|| format!("write history {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// All recorded entries, oldest first; `exa history` numbers them from 1.
+fn load() -> Result<Vec<Value>> {
+    let path = history_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let raw =
+        fs::read_to_string(&path).with_context(|| format!("read history {}", path.display()))?;
+    Ok(raw
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Arguments that repeat history entry `index`. When a body was stored, the
+/// invocation is rebuilt as the recorded flags minus every flag that fed the
+/// body (inputs, dates, domains, `--body*`), plus `--body` with the stored
+/// body. The rerun sends the same request even if stdin, files or the clock
+/// have changed since. Batch runs read their file again.
+pub(crate) fn rerun_args(index: usize) -> Result<Vec<String>> {
+    let entries = load()?;
+    let entry = index
+        .checked_sub(1)
+        .and_then(|idx| entries.get(idx))
+        .ok_or_else(|| anyhow!("no history entry {index} (see `exa history`)"))?;
+    let args: Vec<String> = entry
+        .get("args")
+        .and_then(Value::as_array)
+        .map(|args| {
+            args.iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(replay_args(args, entry.get("body")))
+}
+
+pub(crate) fn replay_args(args: Vec<String>, body: Option<&Value>) -> Vec<String> {
+    let Some(body) = body.filter(|body| body.is_object()) else {
+        return args;
+    };
+    let flag_name = |arg: &str| {
+        arg.split_once('=')
+            .map_or(arg, |(name, _)| name)
+            .to_string()
+    };
+    if args
+        .iter()
+        .any(|arg| BATCH_FLAGS.contains(&flag_name(arg).as_str()))
+    {
+        return args;
+    }
+    let mut out = Vec::new();
+    let mut iter = args.into_iter().peekable();
+    while let Some(arg) = iter.next() {
+        let name = flag_name(&arg);
+        let inline = name.len() < arg.len();
+        if BODY_SWITCHES.contains(&name.as_str()) {
+            continue;
+        }
+        if BODY_LIST_FLAGS.contains(&name.as_str()) {
+            if !inline {
+                while iter.next_if(|next| !next.starts_with('-')).is_some() {}
+            }
+            continue;
+        }
+        if BODY_FLAGS.contains(&name.as_str()) {
+            if !inline {
+                iter.next();
+            }
+            continue;
+        }
+        out.push(arg);
+    }
+    out.push("--body".to_string());
+    out.push(body.to_string());
+    out
+}
+
+pub(crate) fn clear() -> Result<()> {
+    let path = history_path()?;
+    if path.exists() {
+        fs::remove_file(&path).with_context(|| format!("remove {}", path.display()))?;
+    }
+    Ok(())
+}
+
+pub(crate) fn print(last: Option<usize>) -> Result<()> {
+    let entries = load()?;
+    let skip = last.map_or(0, |last| entries.len().saturating_sub(last));
+    for (idx, entry) in entries.iter().enumerate().skip(skip) {
+        let field = |key: &str| entry.get(key).and_then(Value::as_str).unwrap_or("");
+        println!(
+            "{:>4}  {}  {:<12} {:<8} {}",
+            idx + 1,
+            field("timestamp"),
+            field("command"),
+            field("status"),
+            field("summary")
+        );
+    }
+    Ok(())
+}
+
+fn summary(body: &Value) -> String {
+    SUMMARY_KEYS
+        .iter()
+        .filter_map(|key| body.get(*key))
+        .find(|value| value.is_string() || value.is_array())
+        .map(summary_text)
+        .unwrap_or_default()
+}
+
+fn summary_text(value: &Value) -> String {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(","),
+        _ => String::new(),
+    };
+    if text.chars().count() <= SUMMARY_CHARS {
+        return text;
+    }
+    let cut: String = text.chars().take(SUMMARY_CHARS - 1).collect();
+    format!("{cut}…")
+}
+
+fn strip_secrets(args: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if SECRET_FLAGS.contains(&arg.as_str()) {
+            iter.next();
+            continue;
+        }
+        if SECRET_FLAGS
+            .iter()
+            .any(|flag| arg.starts_with(&format!("{flag}=")))
+        {
+            continue;
+        }
+        out.push(arg.clone());
+    }
+    out
+}
+
+fn history_path() -> Result<PathBuf> {
+    config::config_dir()
+        .map(|dir| dir.join("history.jsonl"))
+        .context("cannot locate config dir (set HOME or XDG_CONFIG_HOME)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(raw: &[&str]) -> Vec<String> {
+        raw.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn rerun_replaces_inputs_and_dates_with_the_stored_body() {
+        let body = json!({ "query": "robots", "startPublishedDate": "2026-10-01T00:00:00Z" });
+        let replayed = replay_args(
+            args(&[
+                "--format", "csv", "search", "--query", "-", "--since", "7d", "-o", "out.csv",
+            ]),
+            Some(&body),
+        );
+        assert_eq!(
+            replayed,
+            args(&[
+                "--format",
+                "csv",
+                "search",
+                "-o",
+                "out.csv",
+                "--body",
+                &body.to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn rerun_drops_inline_values_lists_and_switches() {
+        let body = json!({ "urls": ["a", "b"], "text": true });
+        let replayed = replay_args(
+            args(&[
+                "contents",
+                "--urls",
+                "a",
+                "b",
+                "--body-stdin",
+                "--include-domains=x.com",
+                "--exclude-domains",
+                "y.com",
+                "--metadata-only",
+            ]),
+            Some(&body),
+        );
+        assert_eq!(
+            replayed,
+            args(&["contents", "--metadata-only", "--body", &body.to_string()])
+        );
+    }
+
+    #[test]
+    fn rerun_keeps_args_without_a_stored_body_or_for_batches() {
+        let raw = args(&["research", "check", "--task-id", "t1", "--wait"]);
+        assert_eq!(replay_args(raw.clone(), None), raw);
+        let raw = args(&["search", "--queries-file", "q.txt", "--since", "1d"]);
+        assert_eq!(
+            replay_args(raw.clone(), Some(&json!({ "query": "last" }))),
+            raw
+        );
+    }
+
+    #[test]
+    fn secrets_are_stripped_in_both_forms() {
+        let stripped = strip_secrets(&args(&[
+            "--api-key",
+            "sk-1",
+            "--signing-secret=s",
+            "search",
+            "--query",
+            "q",
+        ]));
+        assert_eq!(stripped, args(&["search", "--query", "q"]));
+    }
+
+    #[test]
+    fn summary_prefers_query_and_truncates() {
+        assert_eq!(summary(&json!({ "query": "robots", "url": "u" })), "robots");
+        assert_eq!(summary(&json!({ "urls": ["a", "b"] })), "a,b");
+        let long = summary(&json!({ "query": "x".repeat(200) }));
+        assert_eq!(long.chars().count(), SUMMARY_CHARS);
+        assert!(long.ends_with('…'));
+    }
+}
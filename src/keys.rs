@@ -0,0 +1,122 @@
+use std::sync::{
+    Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum KeyRotation {
+    RoundRobin,
+    Random,
+}
+
+/// The API keys of one run. With a rotation each request picks another key,
+/// and a key the API rejects (401/403) is skipped from then on. Keys are
+/// never printed; messages refer to them by position.
+pub(crate) struct Keys {
+    keys: Vec<String>,
+    rotation: Option<KeyRotation>,
+    next: AtomicUsize,
+    bad: Mutex<Vec<bool>>,
+}
+
+impl Keys {
+    pub(crate) fn new(keys: Vec<String>, rotation: Option<KeyRotation>) -> Self {
+        let bad = Mutex::new(vec![false; keys.len()]);
+        Self {
+            keys,
+            rotation,
+            next: AtomicUsize::new(0),
+            bad,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Position and value of the key for the next request, or `None` once
+    /// every key has been rejected.
+    pub(crate) fn pick(&self) -> Option<(usize, &str)> {
+        let Some(rotation) = self.rotation else {
+            return self.keys.first().map(|key| (0, key.as_str()));
+        };
+        let bad = self.bad.lock().ok()?;
+        let good: Vec<usize> = (0..self.keys.len()).filter(|idx| !bad[*idx]).collect();
+        if good.is_empty() {
+            return None;
+        }
+        let pos = match rotation {
+            KeyRotation::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed),
+            KeyRotation::Random => rand::random_range(0..good.len()),
+        };
+        let idx = good[pos % good.len()];
+        Some((idx, self.keys[idx].as_str()))
+    }
+
+    /// Marks key `idx` as rejected. Returns whether a usable key is left to
+    /// retry with; without a rotation there never is.
+    pub(crate) fn reject(&self, idx: usize) -> bool {
+        if self.rotation.is_none() {
+            return false;
+        }
+        let Ok(mut bad) = self.bad.lock() else {
+            return false;
+        };
+        bad[idx] = true;
+        bad.iter().any(|bad| !bad)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn keys(rotation: Option<KeyRotation>) -> Keys {
+        let keys = ["k0", "k1", "k2"].map(String::from).to_vec();
+        Keys::new(keys, rotation)
+    }
+
+    fn picked(keys: &Keys, times: usize) -> Vec<usize> {
+        (0..times)
+            .map(|_| keys.pick().expect("a usable key").0)
+            .collect()
+    }
+
+    #[test]
+    fn round_robin_cycles_through_every_key() {
+        let keys = keys(Some(KeyRotation::RoundRobin));
+        assert_eq!(picked(&keys, 6), [0, 1, 2, 0, 1, 2]);
+    }
+
+    #[test]
+    fn random_covers_every_key() {
+        let keys = keys(Some(KeyRotation::Random));
+        let seen: HashSet<usize> = picked(&keys, 300).into_iter().collect();
+        assert_eq!(seen, HashSet::from([0, 1, 2]));
+    }
+
+    #[test]
+    fn rejected_keys_are_skipped() {
+        for rotation in [KeyRotation::RoundRobin, KeyRotation::Random] {
+            let keys = keys(Some(rotation));
+            assert!(keys.reject(1));
+            let seen: HashSet<usize> = picked(&keys, 100).into_iter().collect();
+            assert_eq!(seen, HashSet::from([0, 2]));
+            assert!(keys.reject(0));
+            assert!(!keys.reject(2));
+            assert!(keys.pick().is_none());
+        }
+    }
+
+    #[test]
+    fn without_rotation_the_first_key_is_always_used() {
+        let keys = keys(None);
+        assert_eq!(picked(&keys, 3), [0, 0, 0]);
+        assert!(!keys.reject(0));
+        assert_eq!(keys.pick().map(|(idx, _)| idx), Some(0));
+    }
+}
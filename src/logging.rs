@@ -0,0 +1,47 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Map, Value};
+
+pub(crate) struct Logger {
+    file: Mutex<File>,
+    pub(crate) bodies: bool,
+    trace_id: String,
+}
+
+impl Logger {
+    pub(crate) fn open(path: &Path, bodies: bool, trace_id: &str) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("open log file {}", path.display()))?;
+        Ok(Self {
+            file: Mutex::new(file),
+            bodies,
+            trace_id: trace_id.to_string(),
+        })
+    }
+
+    pub(crate) fn write(&self, level: &str, event: &str, fields: Map<String, Value>) {
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".to_string(),
+            Value::String(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+        );
+        line.insert("level".to_string(), Value::String(level.to_string()));
+        line.insert("event".to_string(), Value::String(event.to_string()));
+        line.insert("trace_id".to_string(), Value::String(self.trace_id.clone()));
+        line.extend(fields);
+        let Ok(mut file) = self.file.lock() else {
+            return;
+        };
+        let _ = writeln!(file, "{}", Value::Object(line));
+    }
+}
@@ -1,11 +1,88 @@
-use std::{fs, path::PathBuf, time::Duration};
+mod batch;
+mod config;
+mod dates;
+mod diff;
+mod doctor;
+mod dump;
+mod env;
+mod filter;
+mod hints;
+mod history;
+mod keys;
+mod locale;
+mod logging;
+mod markdown;
+mod metrics;
+mod network;
+mod output;
+mod pretty;
+mod queries;
+mod record;
+mod research;
+mod retry;
+mod schema;
+#[cfg(test)]
+mod testserver;
+mod textdiff;
+mod update;
+
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    fs,
+    io::{self, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering as AtomicOrdering},
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result, anyhow};
-use clap::{Args, Parser, Subcommand};
-use reqwest::Client;
+use chrono::{DateTime, TimeDelta};
+use clap::{ArgAction, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, Request, Response, StatusCode, Url};
 use serde_json::{Map, Value, json};
+use sha2::Sha256;
+
+use crate::{
+    config::Profile,
+    dump::Dumper,
+    env::Env,
+    filter::Filter,
+    keys::{KeyRotation, Keys},
+    logging::Logger,
+    metrics::Metrics,
+    retry::{Breaker, StatusSet},
+};
 
 const DEFAULT_API_BASE: &str = "https://api.exa.ai";
+const DEFAULT_ACCEPT: &str = "application/json";
+const DEFAULT_POOL_IDLE_TIMEOUT: u64 = 90;
+const DEFAULT_MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+const DEFAULT_MAX_RESPONSE_BYTES: usize = 256 * 1024 * 1024;
+const DEFAULT_COMMANDS: [(&str, &str); 5] = [
+    ("search", "--query"),
+    ("answer", "--query"),
+    ("context", "--query"),
+    ("find-similar", "--url"),
+    ("contents", "--urls"),
+];
+const EXIT_NO_RESULTS: i32 = 3;
+const EXIT_PARTIAL: i32 = 4;
+/// Under `--no-fail-on-http-error`, the code an API error would have exited with.
+const EXIT_HTTP_ERROR: i32 = 1;
+/// 128 + SIGINT, as shells report a Ctrl-C.
+const EXIT_INTERRUPTED: i32 = 130;
+/// Query parameters dropped before `--dedupe` compares urls (besides `utm_*`).
+const TRACKING_PARAMS: [&str; 7] = [
+    "fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid", "igshid",
+];
+/// Content fields a search can ask for inline or `--contents-from-search`
+/// fetches afterwards.
+const CONTENT_FIELDS: [&str; 3] = ["text", "highlights", "summary"];
+const SUMMARY_FIELDS: [&str; 3] = ["title", "url", "summary"];
+const METADATA_FIELDS: [&str; 5] = ["id", "url", "title", "author", "publishedDate"];
 const MCP_BASE: &str = "https://mcp.exa.ai/mcp";
 const MCP_TOOLS: [&str; 9] = [
     "web_search_exa",
@@ -28,46 +105,423 @@ struct Cli {
     #[arg(long, global = true)]
     api_base: Option<String>,
 
-    #[arg(long, global = true)]
+    #[arg(long, value_name = "PATH", global = true)]
+    base_path: Option<String>,
+
+    #[arg(long, global = true, overrides_with = "no_pretty")]
     pretty: bool,
 
+    #[arg(long, global = true, overrides_with = "pretty")]
+    no_pretty: bool,
+
+    #[arg(long, global = true)]
+    compact_arrays: bool,
+
+    #[arg(long, global = true)]
+    sort_keys: bool,
+
+    #[arg(long, value_enum, global = true, default_value_t = OutputFormat::Json)]
+    format: OutputFormat,
+
+    #[arg(long, value_name = "TEMPLATE", global = true)]
+    output_template: Option<String>,
+
+    #[arg(long, value_name = "PATHS", global = true, value_delimiter = ',')]
+    fields: Vec<String>,
+
+    #[arg(long, global = true)]
+    export: bool,
+
+    #[arg(long, global = true, conflicts_with_all = ["export", "output_template"])]
+    domains_summary: bool,
+
+    #[arg(long, value_name = "PREFIX", global = true, default_value = "EXA_")]
+    export_prefix: String,
+
+    #[arg(short, long, value_name = "PATH", global = true)]
+    output: Option<PathBuf>,
+
+    #[arg(long, global = true)]
+    summary: bool,
+
+    #[arg(long, value_name = "PATH", global = true, conflicts_with = "output")]
+    merge_output: Option<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        requires = "output",
+        conflicts_with_all = ["export", "output_template", "domains_summary"]
+    )]
+    append: bool,
+
+    #[arg(long, value_name = "N", global = true)]
+    truncate: Option<usize>,
+
+    #[arg(long, global = true)]
+    prettify_dates: bool,
+
+    #[arg(
+        long,
+        value_name = "STRFTIME",
+        global = true,
+        requires = "prettify_dates"
+    )]
+    date_format: Option<String>,
+
+    #[arg(long, value_name = "N", global = true)]
+    max_results: Option<usize>,
+
+    #[arg(long, global = true)]
+    dedupe: bool,
+
+    #[arg(long, global = true)]
+    trim_whitespace: bool,
+
+    /// `FIELD OP VALUE` with >, <, >=, <=, ==, != or contains; repeat to AND.
+    #[arg(long, value_name = "EXPR", global = true, value_parser = filter::parse)]
+    result_filter: Vec<Filter>,
+
+    #[arg(long, value_name = "FIELD", global = true)]
+    sort_by: Option<String>,
+
+    #[arg(long, value_enum, global = true, requires = "sort_by")]
+    sort_order: Option<SortOrder>,
+
+    #[arg(long, global = true)]
+    fail_on_empty: bool,
+
+    #[arg(long, global = true)]
+    no_newline: bool,
+
+    #[arg(long, global = true)]
+    echo_query: bool,
+
+    #[arg(long, global = true)]
+    clipboard: bool,
+
+    #[arg(long, global = true)]
+    clipboard_only: bool,
+
     #[arg(long, value_name = "SECONDS", global = true)]
     timeout: Option<u64>,
 
+    #[arg(long, value_name = "PATH", global = true, requires = "client_key")]
+    client_cert: Option<PathBuf>,
+
+    #[arg(long, value_name = "PATH", global = true, requires = "client_cert")]
+    client_key: Option<PathBuf>,
+
+    #[arg(long, value_name = "PATH", global = true)]
+    ca_cert: Option<PathBuf>,
+
+    #[arg(long, global = true)]
+    insecure: bool,
+
+    #[arg(long, global = true)]
+    http1: bool,
+
+    #[arg(long, value_name = "SECONDS", global = true, default_value_t = DEFAULT_POOL_IDLE_TIMEOUT)]
+    pool_idle_timeout: u64,
+
+    #[arg(long, value_name = "N", global = true)]
+    pool_max_idle_per_host: Option<usize>,
+
+    #[arg(long, value_name = "MIME", global = true, default_value = DEFAULT_ACCEPT)]
+    accept: String,
+
+    #[arg(long, value_name = "DIR", global = true, conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    #[arg(long, value_name = "DIR", global = true)]
+    replay: Option<PathBuf>,
+
+    #[arg(long, value_name = "DIR", global = true)]
+    dump: Option<PathBuf>,
+
+    #[arg(long, value_name = "PATH", global = true)]
+    metrics_file: Option<PathBuf>,
+
+    #[arg(long, global = true)]
+    print_curl: bool,
+
+    #[arg(long, value_name = "SECRET", global = true)]
+    signing_secret: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        global = true,
+        default_value = "x-signature",
+        requires = "signing_secret"
+    )]
+    signing_header: String,
+
+    #[arg(long, value_name = "MS", global = true, default_value_t = 0)]
+    delay: u64,
+
+    #[arg(long, value_name = "N", global = true, default_value_t = 4, value_parser = clap::value_parser!(u64).range(1..))]
+    concurrency: u64,
+
+    #[arg(long, value_name = "N", global = true, default_value_t = 0)]
+    retries: u32,
+
+    #[arg(long, value_name = "N", global = true, default_value_t = 5)]
+    circuit_breaker_threshold: u32,
+
+    #[arg(long, value_name = "CODES", global = true, value_parser = retry::parse_status_set)]
+    retry_on: Option<StatusSet>,
+
+    #[arg(long, value_name = "BYTES", global = true, default_value_t = DEFAULT_MAX_BODY_BYTES)]
+    max_body_bytes: usize,
+
+    #[arg(long, value_name = "BYTES", global = true, default_value_t = DEFAULT_MAX_RESPONSE_BYTES)]
+    max_response_bytes: usize,
+
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    #[arg(long, value_name = "PATH", global = true)]
+    log_file: Option<PathBuf>,
+
+    #[arg(long, value_name = "ID", global = true)]
+    trace_id: Option<String>,
+
+    #[arg(long, value_name = "PATH", global = true)]
+    env_file: Option<PathBuf>,
+
+    #[arg(long, value_enum, global = true)]
+    key_rotation: Option<KeyRotation>,
+
+    #[arg(long, global = true)]
+    no_env: bool,
+
+    #[arg(long, value_name = "NAME", global = true)]
+    profile: Option<String>,
+
+    #[arg(long, global = true)]
+    config_print: bool,
+
+    #[arg(long, global = true)]
+    no_history: bool,
+
+    #[arg(long, global = true)]
+    flatten: bool,
+
+    #[arg(long, global = true, requires = "flatten")]
+    flatten_arrays: bool,
+
+    #[arg(long, value_name = "SECONDS", global = true, value_parser = clap::value_parser!(u64).range(1..))]
+    watch: Option<u64>,
+
+    #[arg(long, global = true, requires = "watch")]
+    watch_diff: bool,
+
+    #[arg(long, value_enum, global = true, default_value_t = BodyMerge::FlagsWin)]
+    body_merge: BodyMerge,
+
+    #[arg(long, global = true)]
+    strict: bool,
+
+    #[arg(long, global = true)]
+    explain_errors: bool,
+
+    #[arg(long, global = true)]
+    no_fail_on_http_error: bool,
+
     #[command(subcommand)]
-    command: Command,
+    command: Option<Command>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Json,
+    Markdown,
+    Csv,
+}
+
+/// How a flag and a `--body`/`--body-file` key setting the same field combine.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum BodyMerge {
+    FlagsWin,
+    BodyWins,
+    ErrorOnConflict,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum AnswerModel {
+    Exa,
+    ExaPro,
+}
+
+impl AnswerModel {
+    fn name(self) -> String {
+        self.to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default()
+    }
 }
 
-#[derive(Subcommand)]
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LinesFormat {
+    Jsonl,
+    JsonlWrapped,
+}
+
+#[derive(Clone, Subcommand)]
 enum Command {
+    #[command(visible_alias = "s")]
     Search(SearchArgs),
+    #[command(visible_alias = "c")]
     Contents(ContentsArgs),
+    #[command(visible_alias = "fs")]
     FindSimilar(FindSimilarArgs),
+    #[command(visible_alias = "a")]
     Answer(AnswerArgs),
+    #[command(visible_alias = "ctx")]
     Context(ContextArgs),
+    #[command(visible_alias = "r")]
     Research(ResearchArgs),
+    Run(RunArgs),
+    Queries(QueriesArgs),
     Mcp(McpArgs),
+    Doctor,
+    Diff(DiffArgs),
+    History(HistoryArgs),
+    Rerun(RerunArgs),
+    Schema(SchemaArgs),
+    Bundle(BundleArgs),
+    SelfUpdate(SelfUpdateArgs),
+}
+
+impl Command {
+    fn name(&self) -> &'static str {
+        match self {
+            Command::Search(_) => "search",
+            Command::Contents(_) => "contents",
+            Command::FindSimilar(_) => "find-similar",
+            Command::Answer(_) => "answer",
+            Command::Context(_) => "context",
+            Command::Research(args) => match args.command {
+                ResearchCommand::Start(_) => "research start",
+                ResearchCommand::Check(_) => "research check",
+            },
+            Command::Run(_) => "run",
+            Command::Queries(_) => "queries",
+            Command::Mcp(_) => "mcp",
+            Command::Doctor => "doctor",
+            Command::Diff(_) => "diff",
+            Command::History(_) => "history",
+            Command::Rerun(_) => "rerun",
+            Command::Schema(_) => "schema",
+            Command::Bundle(_) => "bundle",
+            Command::SelfUpdate(_) => "self-update",
+        }
+    }
 }
 
-#[derive(Args)]
+#[derive(Clone, Args)]
 struct BodyArgs {
     #[arg(long)]
     body: Option<String>,
 
     #[arg(long, value_name = "PATH")]
     body_file: Option<PathBuf>,
+
+    #[arg(long, conflicts_with_all = ["body", "body_file"])]
+    body_stdin: bool,
+
+    /// Defaults to json5 for a `--body-file` ending in `.json5`, else json.
+    #[arg(long, value_enum)]
+    body_format: Option<BodyFormat>,
+
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["body", "body_file", "body_stdin"])]
+    bodies_file: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum BodyFormat {
+    Json,
+    /// JSON plus comments, trailing commas, unquoted keys and single quotes.
+    Json5,
+}
+
+#[derive(Clone, Args)]
+struct DomainArgs {
+    #[arg(long, value_delimiter = ',', num_args = 1..)]
+    include_domains: Vec<String>,
+
+    #[arg(long, value_name = "PATH")]
+    include_domains_file: Option<PathBuf>,
+
+    #[arg(long, value_delimiter = ',', num_args = 1..)]
+    exclude_domains: Vec<String>,
+
+    #[arg(long, value_name = "PATH")]
+    exclude_domains_file: Option<PathBuf>,
+}
+
+#[derive(Clone, Args)]
+struct DateArgs {
+    #[arg(long, value_name = "DURATION", value_parser = dates::parse_duration)]
+    since: Option<TimeDelta>,
+
+    #[arg(long, value_name = "DURATION", value_parser = dates::parse_duration)]
+    until: Option<TimeDelta>,
 }
 
-#[derive(Args)]
+#[derive(Clone, Args)]
 struct SearchArgs {
     #[arg(long)]
     query: Option<String>,
 
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["query", "save_query"])]
+    queries_file: Option<PathBuf>,
+
+    #[arg(long, value_enum, requires = "queries_file", conflicts_with = "query")]
+    output_format: Option<LinesFormat>,
+
+    #[arg(long, value_name = "N")]
+    num_results: Option<u64>,
+
+    #[arg(long)]
+    summary_only: bool,
+
+    #[arg(long, value_name = "CODE", value_parser = locale::parse_country)]
+    country: Option<String>,
+
+    #[arg(long, value_name = "F", value_parser = parse_score)]
+    min_score: Option<f64>,
+
+    #[arg(long, value_name = "NAME")]
+    save_query: Option<String>,
+
+    #[arg(long, conflicts_with = "queries_file")]
+    contents_from_search: bool,
+
+    #[arg(long, conflicts_with = "queries_file")]
+    preview_first: bool,
+
+    #[arg(long, short = 'y', requires = "preview_first")]
+    yes: bool,
+
+    #[command(flatten)]
+    dates: DateArgs,
+
+    #[command(flatten)]
+    domains: DomainArgs,
+
     #[command(flatten)]
     body: BodyArgs,
 }
 
-#[derive(Args)]
+#[derive(Clone, Args)]
 struct ContentsArgs {
     #[arg(long, value_delimiter = ',', num_args = 1..)]
     urls: Vec<String>,
@@ -75,29 +529,97 @@ struct ContentsArgs {
     #[arg(long, value_delimiter = ',', num_args = 1..)]
     ids: Vec<String>,
 
+    #[arg(long)]
+    metadata_only: bool,
+
+    #[arg(long, value_name = "N")]
+    extras_links: Option<u32>,
+
+    #[arg(long, value_name = "N")]
+    extras_images: Option<u32>,
+
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    batch_size: Option<u64>,
+
+    #[arg(long, requires = "batch_size")]
+    stream_results: bool,
+
+    #[arg(long, requires = "stream_results")]
+    ordered: bool,
+
+    #[arg(long, requires = "batch_size", conflicts_with = "stream_results")]
+    partial_on_interrupt: bool,
+
+    #[arg(long)]
+    fail_on_partial: bool,
+
+    #[arg(long, conflicts_with_all = ["metadata_only", "stream_results"])]
+    as_markdown: bool,
+
+    #[arg(long, value_name = "DIR", requires = "as_markdown")]
+    output_dir: Option<PathBuf>,
+
+    #[arg(long, conflicts_with_all = ["metadata_only", "stream_results", "as_markdown"])]
+    diff_text: bool,
+
+    #[arg(long, conflicts_with_all = ["metadata_only", "stream_results", "diff_text"])]
+    skip_unchanged: bool,
+
     #[command(flatten)]
     body: BodyArgs,
 }
 
-#[derive(Args)]
+#[derive(Clone, Args)]
 struct FindSimilarArgs {
     #[arg(long)]
     url: Option<String>,
 
+    #[arg(long)]
+    category: Option<String>,
+
+    #[command(flatten)]
+    dates: DateArgs,
+
+    #[command(flatten)]
+    domains: DomainArgs,
+
     #[command(flatten)]
     body: BodyArgs,
 }
 
-#[derive(Args)]
+#[derive(Clone, Args)]
 struct AnswerArgs {
     #[arg(long)]
     query: Option<String>,
 
+    #[arg(long)]
+    citations_only: bool,
+
+    #[arg(long, overrides_with = "no_stream")]
+    stream: bool,
+
+    #[arg(long, overrides_with = "stream")]
+    no_stream: bool,
+
+    #[arg(long, value_enum)]
+    model: Option<AnswerModel>,
+
+    #[arg(long, value_name = "STR", conflicts_with = "system_prompt_file")]
+    system_prompt: Option<String>,
+
+    #[arg(long, value_name = "PATH")]
+    system_prompt_file: Option<PathBuf>,
+
+    /// `answer_model` from the config profile, used when neither `--model`
+    /// nor the body picks one.
+    #[arg(skip)]
+    default_model: Option<AnswerModel>,
+
     #[command(flatten)]
     body: BodyArgs,
 }
 
-#[derive(Args)]
+#[derive(Clone, Args)]
 struct ContextArgs {
     #[arg(long)]
     query: Option<String>,
@@ -106,46 +628,133 @@ struct ContextArgs {
     body: BodyArgs,
 }
 
-#[derive(Subcommand)]
+#[derive(Clone, Args)]
+struct SelfUpdateArgs {
+    #[arg(long)]
+    check_only: bool,
+}
+
+#[derive(Clone, Args)]
+struct BundleArgs {
+    #[arg(long)]
+    query: String,
+
+    #[arg(long, value_name = "N")]
+    num_results: Option<u32>,
+
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    top: usize,
+}
+
+#[derive(Clone, Subcommand)]
 enum ResearchCommand {
     Start(ResearchStartArgs),
     Check(ResearchCheckArgs),
 }
 
-#[derive(Args)]
+#[derive(Clone, Args)]
 struct ResearchArgs {
     #[command(subcommand)]
     command: ResearchCommand,
 }
 
-#[derive(Args)]
+#[derive(Clone, Args)]
 struct ResearchStartArgs {
     #[arg(long)]
     instructions: Option<String>,
 
+    #[arg(long, value_name = "KEY")]
+    idempotency_key: Option<String>,
+
     #[command(flatten)]
     body: BodyArgs,
 }
 
-#[derive(Args)]
+#[derive(Clone, Args)]
 struct ResearchCheckArgs {
     #[arg(long)]
     task_id: Option<String>,
+
+    #[arg(long)]
+    wait: bool,
+
+    #[arg(long, value_name = "SECONDS", default_value_t = 600, requires = "wait")]
+    wait_timeout: u64,
+
+    #[arg(long, value_name = "SECONDS", default_value_t = 5, requires = "wait")]
+    poll_interval: u64,
+
+    #[arg(long, value_name = "SECONDS", requires = "wait", value_parser = clap::value_parser!(u64).range(1..))]
+    poll_timeout: Option<u64>,
+}
+
+#[derive(Clone, Args)]
+struct DiffArgs {
+    old: PathBuf,
+
+    #[arg(required_unless_present = "query")]
+    new: Option<PathBuf>,
+
+    #[arg(long, conflicts_with = "new")]
+    query: Option<String>,
 }
 
-#[derive(Subcommand)]
+#[derive(Clone, Args)]
+struct RunArgs {
+    name: String,
+}
+
+#[derive(Clone, Args)]
+struct RerunArgs {
+    index: usize,
+}
+
+#[derive(Clone, Args)]
+struct SchemaArgs {
+    endpoint: String,
+
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Clone, Subcommand)]
+enum HistoryCommand {
+    Clear,
+}
+
+#[derive(Clone, Args)]
+struct HistoryArgs {
+    #[command(subcommand)]
+    command: Option<HistoryCommand>,
+
+    #[arg(long, value_name = "N")]
+    last: Option<usize>,
+}
+
+#[derive(Clone, Subcommand)]
+enum QueriesCommand {
+    List,
+}
+
+#[derive(Clone, Args)]
+struct QueriesArgs {
+    #[command(subcommand)]
+    command: QueriesCommand,
+}
+
+#[derive(Clone, Subcommand)]
 enum McpCommand {
     Url(McpUrlArgs),
     Tools,
 }
 
-#[derive(Args)]
+#[derive(Clone, Args)]
 struct McpArgs {
     #[command(subcommand)]
     command: McpCommand,
 }
 
-#[derive(Args)]
+#[derive(Clone, Args)]
 struct McpUrlArgs {
     #[arg(long, value_name = "LIST|all")]
     tools: Option<String>,
@@ -153,171 +762,1845 @@ struct McpUrlArgs {
 
 #[tokio::main]
 async fn main() {
-    if let Err(err) = run().await {
-        eprintln!("error: {err}");
-        std::process::exit(1);
+    // Dropping the run future on Ctrl-C cancels in-flight requests and
+    // research polling. If the handler cannot be installed the branch is
+    // disabled and Ctrl-C keeps its default behaviour.
+    let run = run(std::env::args_os().collect());
+    tokio::pin!(run);
+    let result = tokio::select! {
+        result = &mut run => result,
+        Ok(()) = tokio::signal::ctrl_c() => {
+            if !batch::flushes_on_interrupt() {
+                eprintln!("interrupted");
+                std::process::exit(EXIT_INTERRUPTED);
+            }
+            // The batch saw the signal too and returns what it collected.
+            run.await
+        }
+    };
+    match result {
+        Ok(0) => {}
+        Ok(code) => std::process::exit(code),
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(network::exit_code(&err));
+        }
     }
 }
 
-async fn run() -> Result<()> {
-    let cli = Cli::parse();
+async fn run(args: Vec<OsString>) -> Result<i32> {
+    let config = config::load();
+    let args = apply_default_command(
+        args,
+        config
+            .as_ref()
+            .ok()
+            .and_then(|config| config.default_command.as_deref()),
+    )?;
+    let argv: Vec<String> = args
+        .iter()
+        .skip(1)
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let mut cli = Cli::parse_from(args);
+    let command = cli.command.take();
+    if command.is_none() && !cli.config_print {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::MissingSubcommand,
+                "a subcommand is required",
+            )
+            .exit();
+    }
+
+    if cli.strict
+        && let Some(command) = &command
+    {
+        check_strict(&cli, command)?;
+    }
 
-    if let Command::Mcp(cmd) = &cli.command {
-        return handle_mcp(&cmd.command);
+    if let Some(Command::Doctor) = &command {
+        return doctor::run(&cli, config).await;
     }
+    let config = config?;
 
-    let api_key = cli
-        .api_key
-        .or_else(|| std::env::var("EXA_API_KEY").ok())
-        .context("EXA_API_KEY missing")?;
-    let api_base = cli
-        .api_base
-        .or_else(|| std::env::var("EXA_API_BASE").ok())
-        .unwrap_or_else(|| DEFAULT_API_BASE.to_string());
-    let api_base = api_base.trim_end_matches('/');
-    let timeout = Duration::from_secs(cli.timeout.unwrap_or(30));
-    let client = Client::builder().timeout(timeout).build()?;
-
-    let payload = match cli.command {
-        Command::Search(args) => {
-            let mut body = load_body(&args.body)?;
-            if let Some(query) = args.query {
-                body.insert("query".to_string(), Value::String(query));
-            }
-            ensure_string_field(&body, "query")?;
-            exa_post(&client, api_base, &api_key, "/search", Value::Object(body)).await?
+    if let Some(Command::Mcp(cmd)) = &command {
+        handle_mcp(&cmd.command)?;
+        return Ok(0);
+    }
+    if let Some(Command::History(cmd)) = &command {
+        match cmd.command {
+            Some(HistoryCommand::Clear) => history::clear()?,
+            None => history::print(cmd.last)?,
         }
-        Command::Contents(args) => {
-            let mut body = load_body(&args.body)?;
-            if let Some(urls) = normalize_list(&args.urls) {
-                body.insert("urls".to_string(), Value::Array(urls));
-            }
-            if let Some(ids) = normalize_list(&args.ids) {
-                body.insert("ids".to_string(), Value::Array(ids));
-            }
-            ensure_any_field(&body, &["urls", "ids"])?;
-            exa_post(&client, api_base, &api_key, "/contents", Value::Object(body)).await?
+        return Ok(0);
+    }
+    if let Some(Command::Rerun(cmd)) = &command {
+        let args = history::rerun_args(cmd.index)?;
+        let shown: Vec<String> = args.iter().map(|arg| output::shell_word(arg)).collect();
+        eprintln!("exa {}", shown.join(" "));
+        let mut argv = vec![OsString::from("exa")];
+        argv.extend(args.into_iter().map(OsString::from));
+        return Box::pin(run(argv)).await;
+    }
+    if let Some(Command::Schema(cmd)) = &command {
+        schema::print(&cmd.endpoint, cmd.json)?;
+        return Ok(0);
+    }
+    if let Some(Command::SelfUpdate(cmd)) = &command {
+        let client = build_client(&cli, cli.timeout.unwrap_or(120))?;
+        return update::run(&client, cmd.check_only).await;
+    }
+    if let Some(Command::Queries(cmd)) = &command {
+        match cmd.command {
+            QueriesCommand::List => queries::list()?.iter().for_each(|name| println!("{name}")),
         }
-        Command::FindSimilar(args) => {
-            let mut body = load_body(&args.body)?;
-            if let Some(url) = args.url {
-                body.insert("url".to_string(), Value::String(url));
-            }
-            ensure_string_field(&body, "url")?;
-            exa_post(
-                &client,
-                api_base,
-                &api_key,
-                "/findSimilar",
-                Value::Object(body),
-            )
-            .await?
+        return Ok(0);
+    }
+
+    let env = Env::load(cli.env_file.as_deref(), cli.no_env)?;
+    let profile_name = cli.profile.clone().or_else(|| env.var("EXA_PROFILE"));
+    let profile = config.profile(profile_name.as_deref())?;
+    if cli.config_print {
+        let effective = effective_config(&cli, &env, &profile, profile_name.as_deref());
+        println!("{}", serde_json::to_string_pretty(&effective)?);
+        return Ok(0);
+    }
+    let Some(mut command) = command else {
+        unreachable!("checked after parsing")
+    };
+    // Read once up front so --watch repeats the same query and body.
+    read_stdin_inputs(&mut command, &mut io::stdin())?;
+    if let Command::Answer(args) = &mut command {
+        args.default_model = answer_model(&profile)?;
+    }
+    if cli.summary && cli.output.is_none() && !matches!(command, Command::Diff(_)) {
+        return Err(anyhow!("--summary needs --output for the full response"));
+    }
+    let api_key = match (resolve_api_key(&cli, &env, &profile), &cli.replay) {
+        (Some((key, _)), _) => key,
+        (None, Some(_)) => String::new(),
+        (None, None) if matches!(&command, Command::Diff(args) if args.query.is_none()) => {
+            String::new()
         }
-        Command::Answer(args) => {
-            let mut body = load_body(&args.body)?;
-            if let Some(query) = args.query {
-                body.insert("query".to_string(), Value::String(query));
-            }
-            ensure_string_field(&body, "query")?;
-            body.insert("stream".to_string(), Value::Bool(false));
-            exa_post(&client, api_base, &api_key, "/answer", Value::Object(body)).await?
-        }
-        Command::Context(args) => {
-            let mut body = load_body(&args.body)?;
-            if let Some(query) = args.query {
-                body.insert("query".to_string(), Value::String(query));
+        (None, None) => return Err(anyhow!("EXA_API_KEY missing")),
+    };
+    let (api_base, _) = resolve_api_base(&cli, &env, &profile);
+    let api_base = api_base.trim_end_matches('/');
+    let base_url = parse_api_base(api_base)?;
+    if cli.insecure {
+        ensure_insecure_allowed(&base_url)?;
+        eprintln!(
+            "warning: --insecure disables TLS certificate verification; use only for local testing"
+        );
+    }
+    let base_path = normalize_base_path(cli.base_path.as_deref().unwrap_or(""));
+    let date_format = cli
+        .date_format
+        .as_deref()
+        .unwrap_or(dates::DEFAULT_DATE_FORMAT);
+    dates::validate_date_format(date_format)?;
+    let trace_id = cli
+        .trace_id
+        .clone()
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let timeout = cli.timeout.or(profile.timeout).unwrap_or(30);
+    let api = Api {
+        client: build_client(&cli, timeout)?,
+        timeout: Duration::from_secs(timeout),
+        base: format!("{api_base}{base_path}"),
+        keys: Keys::new(
+            key_pool(api_key, &profile, cli.key_rotation.is_some()),
+            cli.key_rotation,
+        ),
+        accept: cli.accept.clone(),
+        raw: !is_json_mime(&cli.accept),
+        explain_errors: cli.explain_errors,
+        content_defaults: profile.contents.fields(),
+        fail_on_http_error: !cli.no_fail_on_http_error,
+        http_failed: AtomicBool::new(false),
+        max_body_bytes: cli.max_body_bytes,
+        max_response_bytes: cli.max_response_bytes,
+        verbose: cli.verbose,
+        log: cli
+            .log_file
+            .as_deref()
+            .map(|path| Logger::open(path, cli.verbose >= 2, &trace_id))
+            .transpose()?,
+        record: cli.record.clone(),
+        replay: cli.replay.clone(),
+        dump: cli.dump.as_deref().map(Dumper::new).transpose()?,
+        print_curl: cli.print_curl,
+        signing: cli
+            .signing_secret
+            .clone()
+            .map(|secret| (cli.signing_header.clone(), secret)),
+        trace_id,
+        delay: Duration::from_millis(cli.delay),
+        last_request: tokio::sync::Mutex::new(None),
+        retries: cli.retries,
+        retry_on: cli.retry_on.clone().unwrap_or_default(),
+        breaker: Breaker::new(cli.circuit_breaker_threshold),
+        metrics: cli.metrics_file.is_some().then(Metrics::default),
+    };
+
+    let Some(interval) = cli.watch else {
+        let name = command.name();
+        let label = request_label(&command);
+        let mut sent = None;
+        let result = run_once(&cli, command, &api, &mut None, &mut sent)
+            .await
+            .map(|code| match code {
+                EXIT_INTERRUPTED => code,
+                _ if api.http_failed.load(AtomicOrdering::SeqCst) => EXIT_HTTP_ERROR,
+                code => code,
+            });
+        write_metrics(&cli, &api);
+        if !cli.no_history {
+            let status = match &result {
+                Ok(0) => "ok".to_string(),
+                Ok(code) => format!("exit {code}"),
+                Err(_) => "error".to_string(),
+            };
+            let entry = history::Entry {
+                command: name,
+                args: &argv,
+                body: sent,
+                label,
+                status,
+            };
+            if let Err(err) = history::append(entry) {
+                eprintln!("warning: {err}");
             }
-            ensure_string_field(&body, "query")?;
-            exa_post(&client, api_base, &api_key, "/context", Value::Object(body)).await?
         }
-        Command::Research(cmd) => match cmd.command {
-            ResearchCommand::Start(args) => {
-                let mut body = load_body(&args.body)?;
-                if let Some(instructions) = args.instructions {
-                    body.insert("instructions".to_string(), Value::String(instructions));
-                }
-                ensure_string_field(&body, "instructions")?;
-                exa_post(
-                    &client,
-                    api_base,
-                    &api_key,
-                    "/research/v0/tasks",
-                    Value::Object(body),
-                )
-                .await?
-            }
-            ResearchCommand::Check(args) => {
-                let task_id = args.task_id.context("task_id missing")?;
-                let path = format!("/research/v0/tasks/{task_id}");
-                exa_get(&client, api_base, &api_key, &path).await?
-            }
-        },
-        Command::Mcp(_) => unreachable!("mcp handled earlier"),
+        return result;
     };
+    let mut previous = None;
+    loop {
+        if let Err(err) = run_once(&cli, command.clone(), &api, &mut previous, &mut None).await {
+            eprintln!("error: {err}");
+        }
+        write_metrics(&cli, &api);
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+    }
+}
 
-    let output = if cli.pretty {
-        serde_json::to_string_pretty(&payload)?
-    } else {
-        serde_json::to_string(&payload)?
+/// Sends the request for `command` and prints the response. Under
+/// `--watch-diff`, `previous` holds the last result set and output is skipped
+/// while it stays the same.
+async fn run_once(
+    cli: &Cli,
+    command: Command,
+    api: &Api,
+    previous: &mut Option<Value>,
+    // The command's own assembled request, kept for history. Commands built
+    // from fixed flags (bundle, diff, run) leave it unset.
+    sent: &mut Option<Value>,
+) -> Result<i32> {
+    let mut fail_on_empty = cli.fail_on_empty;
+    let mut partial = false;
+    let mut interrupted = false;
+    // `--queries-file` output: NDJSON lines instead of one JSON document.
+    let mut lines = None;
+    let is_diff = matches!(command, Command::Diff(_));
+    let merge = cli.body_merge;
+    let label = cli.echo_query.then(|| request_label(&command)).flatten();
+    let mut payload = if let Some((path, endpoint, name)) = bodies_file(&command) {
+        // Lines are whole responses, not search results.
+        let unsupported = [
+            ("--dedupe", cli.dedupe),
+            ("--trim-whitespace", cli.trim_whitespace),
+            ("--merge-output", cli.merge_output.is_some()),
+        ];
+        if let Some((flag, _)) = unsupported.iter().find(|(_, set)| *set) {
+            return Err(anyhow!("{flag} cannot be used with --bodies-file"));
+        }
+        let ignored = bodies_file_ignored(&command);
+        if !ignored.is_empty() {
+            return Err(anyhow!(
+                "{} cannot be used with --bodies-file: each line is sent as it is",
+                ignored.join(", ")
+            ));
+        }
+        let bodies = batch::read_bodies(path)?;
+        for (line, body) in &bodies {
+            check_body_fields(cli, name, body)
+                .with_context(|| format!("{} line {line}", path.display()))?;
+        }
+        let outcome = batch::bodies(api, endpoint, bodies, cli.concurrency as usize).await?;
+        partial = outcome.failed > 0;
+        lines = Some(batch::Lines::Bodies);
+        outcome.payload
+    } else {
+        match command {
+            Command::Search(args) => {
+                let mut body = load_body(&args.body)?;
+                if let Some(query) = args.query {
+                    merge_flag(&mut body, "query", Value::String(query), merge)?;
+                }
+                apply_dates(&args.dates, &mut body, merge)?;
+                apply_domains(&args.domains, &mut body, merge)?;
+                if let Some(country) = args.country {
+                    merge_flag(&mut body, "userLocation", Value::String(country), merge)?;
+                }
+                if let Some(n) = args.num_results {
+                    merge_flag(&mut body, "numResults", json!(n), merge)?;
+                }
+                if args.summary_only {
+                    let contents = body
+                        .entry("contents")
+                        .or_insert_with(|| Value::Object(Map::new()));
+                    let Value::Object(contents) = contents else {
+                        return Err(anyhow!("contents in body must be an object"));
+                    };
+                    merge_flag(contents, "summary", Value::Bool(true), merge)?;
+                }
+                check_body_fields(cli, "search", &body)?;
+                apply_content_defaults(api, &mut body, "contents")?;
+                let project = args.summary_only.then_some(&SUMMARY_FIELDS[..]);
+                if let Some(path) = &args.queries_file {
+                    if body.contains_key("query") {
+                        let message = "the body's query is replaced by each --queries-file line";
+                        if cli.strict {
+                            return Err(anyhow!("{message} (--strict)"));
+                        }
+                        eprintln!("warning: {message}");
+                    }
+                    let queries = read_list_file(path)?;
+                    let opts = batch::SearchBatch {
+                        concurrency: cli.concurrency as usize,
+                        project,
+                        min_score: args.min_score,
+                        // Filters run later, so only a plain cap can stop early.
+                        max_results: cli
+                            .max_results
+                            .filter(|_| !cli.dedupe && cli.result_filter.is_empty()),
+                    };
+                    let outcome = batch::searches(api, body, &queries, &opts).await?;
+                    partial = outcome.failed > 0;
+                    let wrap = args.output_format != Some(LinesFormat::Jsonl);
+                    lines = Some(batch::Lines::Queries { wrap });
+                    outcome.payload
+                } else {
+                    ensure_string_field(&body, "query")?;
+                    if let Some(name) = &args.save_query {
+                        let path = queries::save(name, &body)?;
+                        eprintln!("saved query {name} to {}", path.display());
+                    }
+                    if args.preview_first && !preview_search(api, &body, args.yes).await? {
+                        eprintln!("skipped the full search");
+                        return Ok(0);
+                    }
+                    *sent = Some(Value::Object(body.clone()));
+                    let contents_options = body.get("contents").cloned();
+                    let mut payload = exa_post(api, "/search", Value::Object(body)).await?;
+                    if let Some(min) = args.min_score
+                        && let Some(results) = result_list_mut(&mut payload)
+                    {
+                        let dropped = drop_below_score(results, min);
+                        if api.verbose >= 1 {
+                            eprintln!("filtered {dropped} results below --min-score {min}");
+                        }
+                    }
+                    if args.contents_from_search {
+                        attach_contents(api, &mut payload, contents_options).await?;
+                    }
+                    if let Some(fields) = project {
+                        project_results(&mut payload, fields);
+                    }
+                    payload
+                }
+            }
+            Command::Diff(args) => {
+                let old = diff::load_results(&args.old)?;
+                let new = match (&args.new, args.query) {
+                    (Some(path), _) => diff::load_results(path)?,
+                    (None, Some(query)) => {
+                        let payload = exa_post(api, "/search", json!({ "query": query })).await?;
+                        result_list(&payload).cloned().unwrap_or_default()
+                    }
+                    (None, None) => unreachable!("clap requires NEW or --query"),
+                };
+                let diff = diff::diff(&old, &new);
+                if cli.summary && cli.output.is_none() {
+                    println!("{}", diff::summary(&diff));
+                    return Ok(0);
+                }
+                diff
+            }
+            Command::Bundle(args) => {
+                let mut search = json!({ "query": args.query });
+                if let Some(n) = args.num_results {
+                    search["numResults"] = json!(n);
+                }
+                let answer = json!({ "query": args.query, "stream": false });
+                // Search and answer are independent; contents needs the search.
+                let (search, answer) = tokio::join!(
+                    exa_post(api, "/search", search),
+                    exa_post(api, "/answer", answer)
+                );
+                let (search, answer) = (search?, answer?);
+                let urls: Vec<Value> = result_list(&search)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|result| result.get("url").cloned())
+                    .take(args.top)
+                    .collect();
+                let contents = if urls.is_empty() {
+                    json!({ "results": [], "errors": [] })
+                } else {
+                    let body = json!({ "urls": urls, "text": true });
+                    let mut contents = exa_post(api, "/contents", body).await?;
+                    split_contents_errors(&mut contents);
+                    contents
+                };
+                json!({ "search": search, "contents": contents, "answer": answer })
+            }
+            Command::Run(args) => {
+                let body = queries::load(&args.name)?;
+                ensure_string_field(&body, "query")?;
+                check_body_fields(cli, "search", &body)?;
+                exa_post(api, "/search", Value::Object(body)).await?
+            }
+            Command::Contents(args) => {
+                let mut body = load_body(&args.body)?;
+                if let Some(urls) = normalize_list(&args.urls) {
+                    merge_flag(&mut body, "urls", Value::Array(urls), merge)?;
+                }
+                if let Some(ids) = normalize_list(&args.ids) {
+                    merge_flag(&mut body, "ids", Value::Array(ids), merge)?;
+                }
+                apply_extras(&args, &mut body, merge)?;
+                let dropped = dedupe_contents_targets(&mut body);
+                if dropped > 0 && api.verbose >= 1 {
+                    eprintln!("dropped {dropped} duplicate urls/ids from the request");
+                }
+                ensure_any_field(&body, &["urls", "ids"])?;
+                check_body_fields(cli, "contents", &body)?;
+                if args.as_markdown || args.diff_text || args.skip_unchanged {
+                    body.entry("text").or_insert(Value::Bool(true));
+                }
+                apply_content_defaults(api, &mut body, "")?;
+                if args.metadata_only {
+                    body.insert("text".to_string(), Value::Bool(false));
+                    body.remove("highlights");
+                    body.remove("summary");
+                }
+                *sent = Some(Value::Object(body.clone()));
+                let mut lost_batches = 0;
+                let mut payload = match args.batch_size {
+                    Some(batch_size) => {
+                        let stream = if args.stream_results {
+                            Some(batch::Stream {
+                                out: stream_writer(cli)?,
+                                fields: &cli.fields,
+                            })
+                        } else {
+                            None
+                        };
+                        let opts = batch::ContentsBatch {
+                            batch_size: batch_size as usize,
+                            concurrency: cli.concurrency as usize,
+                            stream,
+                            ordered: args.ordered,
+                            max_results: cli.max_results,
+                            project: args.metadata_only.then_some(&METADATA_FIELDS[..]),
+                            partial_on_interrupt: args.partial_on_interrupt,
+                        };
+                        match batch::contents(api, body, opts).await? {
+                            batch::ContentsOutcome::Merged {
+                                payload,
+                                failed_batches,
+                            } => {
+                                lost_batches = failed_batches;
+                                payload
+                            }
+                            batch::ContentsOutcome::Interrupted(payload) => {
+                                interrupted = true;
+                                payload
+                            }
+                            batch::ContentsOutcome::Streamed {
+                                failed,
+                                failed_batches,
+                            } => {
+                                let partial =
+                                    failed_batches > 0 || (args.fail_on_partial && failed > 0);
+                                return Ok(if partial { EXIT_PARTIAL } else { 0 });
+                            }
+                        }
+                    }
+                    None => exa_post(api, "/contents", Value::Object(body)).await?,
+                };
+                let (ok, failed) = split_contents_errors(&mut payload);
+                if api.verbose >= 1 {
+                    eprintln!("{ok} ok, {failed} failed");
+                }
+                partial = lost_batches > 0 || (args.fail_on_partial && failed > 0);
+                let overlapping = count_duplicate_results(&payload);
+                if overlapping > 0 {
+                    eprintln!(
+                        "warning: {overlapping} results repeat a document already returned (overlapping urls and ids?)"
+                    );
+                }
+                if args.metadata_only {
+                    project_results(&mut payload, &METADATA_FIELDS);
+                }
+                if args.skip_unchanged
+                    && let Some(results) = result_list_mut(&mut payload)
+                {
+                    // A client-side filter: every page was fetched (and billed)
+                    // above, then compared with the `--diff-text` hashes.
+                    let changes = textdiff::compare(results)?;
+                    let urls = |key: &str| -> Vec<String> {
+                        changes[key]
+                            .as_array()
+                            .map(Vec::as_slice)
+                            .unwrap_or_default()
+                            .iter()
+                            .filter_map(Value::as_str)
+                            .map(str::to_string)
+                            .collect()
+                    };
+                    let labels = [
+                        ("new", "added"),
+                        ("changed", "changed"),
+                        ("unchanged", "unchanged"),
+                    ];
+                    for (label, key) in labels {
+                        for url in urls(key) {
+                            eprintln!("{label:<9} {url}");
+                        }
+                    }
+                    let unchanged: HashSet<String> = urls("unchanged").into_iter().collect();
+                    results.retain(|result| {
+                        result
+                            .get("url")
+                            .and_then(Value::as_str)
+                            .is_none_or(|url| !unchanged.contains(url))
+                    });
+                }
+                if args.diff_text {
+                    let results = result_list(&payload).map(Vec::as_slice).unwrap_or_default();
+                    let mut changes = textdiff::compare(results)?;
+                    changes["errors"] = payload.get("errors").cloned().unwrap_or(json!([]));
+                    payload = changes;
+                }
+                if args.as_markdown {
+                    let results = result_list(&payload).map(Vec::as_slice).unwrap_or_default();
+                    match &args.output_dir {
+                        Some(dir) => {
+                            let paths = markdown::write_notes(dir, results)?;
+                            eprintln!("wrote {} notes to {}", paths.len(), dir.display());
+                        }
+                        None => {
+                            let notes: Vec<String> =
+                                results.iter().map(markdown::result_markdown).collect();
+                            print!("{}", notes.join("\n---\n\n"));
+                        }
+                    }
+                    return Ok(if partial { EXIT_PARTIAL } else { 0 });
+                }
+                payload
+            }
+            Command::FindSimilar(args) => {
+                let mut body = load_body(&args.body)?;
+                if let Some(url) = args.url {
+                    merge_flag(&mut body, "url", Value::String(url), merge)?;
+                }
+                if let Some(category) = args.category {
+                    merge_flag(&mut body, "category", Value::String(category), merge)?;
+                }
+                apply_dates(&args.dates, &mut body, merge)?;
+                apply_domains(&args.domains, &mut body, merge)?;
+                ensure_string_field(&body, "url")?;
+                check_body_fields(cli, "find-similar", &body)?;
+                apply_content_defaults(api, &mut body, "contents")?;
+                *sent = Some(Value::Object(body.clone()));
+                exa_post(api, "/findSimilar", Value::Object(body)).await?
+            }
+            Command::Answer(args) => {
+                let mut body = load_body(&args.body)?;
+                if let Some(query) = args.query {
+                    merge_flag(&mut body, "query", Value::String(query), merge)?;
+                }
+                ensure_string_field(&body, "query")?;
+                check_body_fields(cli, "answer", &body)?;
+                let flag = match (args.stream, args.no_stream) {
+                    (true, _) => Some(true),
+                    (_, true) => Some(false),
+                    _ => None,
+                };
+                if let Some(flag) = flag {
+                    merge_flag(&mut body, "stream", Value::Bool(flag), merge)?;
+                }
+                if let Some(model) = args.model {
+                    merge_flag(&mut body, "model", Value::String(model.name()), merge)?;
+                } else if let Some(model) = args.default_model {
+                    body.entry("model")
+                        .or_insert_with(|| Value::String(model.name()));
+                }
+                if let Some(prompt) = inline_or_file(
+                    "system-prompt",
+                    &args.system_prompt,
+                    &args.system_prompt_file,
+                )? {
+                    let prompt = prompt.trim();
+                    if prompt.is_empty() {
+                        return Err(anyhow!("--system-prompt is empty"));
+                    }
+                    merge_flag(
+                        &mut body,
+                        "systemPrompt",
+                        Value::String(prompt.to_string()),
+                        merge,
+                    )?;
+                }
+                let stream = body.get("stream").and_then(Value::as_bool).unwrap_or(false);
+                if stream && args.citations_only {
+                    return Err(anyhow!("--citations-only needs a non-streaming answer"));
+                }
+                body.insert("stream".to_string(), Value::Bool(stream));
+                *sent = Some(Value::Object(body.clone()));
+                let payload = exa_post(api, "/answer", Value::Object(body)).await?;
+                if stream {
+                    sse_events(&payload)
+                } else if args.citations_only {
+                    fail_on_empty = true;
+                    citations(&payload)
+                } else {
+                    payload
+                }
+            }
+            Command::Context(args) => {
+                let mut body = load_body(&args.body)?;
+                if let Some(query) = args.query {
+                    merge_flag(&mut body, "query", Value::String(query), merge)?;
+                }
+                ensure_string_field(&body, "query")?;
+                check_body_fields(cli, "context", &body)?;
+                *sent = Some(Value::Object(body.clone()));
+                exa_post(api, "/context", Value::Object(body)).await?
+            }
+            Command::Research(cmd) => match cmd.command {
+                ResearchCommand::Start(args) => {
+                    let mut body = load_body(&args.body)?;
+                    if let Some(instructions) = args.instructions {
+                        merge_flag(
+                            &mut body,
+                            "instructions",
+                            Value::String(instructions),
+                            merge,
+                        )?;
+                    }
+                    ensure_string_field(&body, "instructions")?;
+                    check_body_fields(cli, "research", &body)?;
+                    // One key per invocation, reused by every retry of this request.
+                    let key = args
+                        .idempotency_key
+                        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+                    let opts = RequestOptions {
+                        headers: &[("idempotency-key", key.as_str())],
+                        ..RequestOptions::default()
+                    };
+                    *sent = Some(Value::Object(body.clone()));
+                    exa_post_with(api, "/research/v0/tasks", Value::Object(body), &opts).await?
+                }
+                ResearchCommand::Check(args) => {
+                    let task_id = args.task_id.context("task_id missing")?;
+                    if args.wait {
+                        let wait = research::Wait {
+                            timeout: Duration::from_secs(args.wait_timeout),
+                            interval: Duration::from_secs(args.poll_interval),
+                            poll_timeout: args.poll_timeout.map(Duration::from_secs),
+                        };
+                        research::wait(api, &task_id, &wait).await?
+                    } else {
+                        let path = format!("/research/v0/tasks/{task_id}");
+                        exa_get(api, &path).await?
+                    }
+                }
+            },
+            Command::Mcp(_)
+            | Command::Queries(_)
+            | Command::Doctor
+            | Command::History(_)
+            | Command::Rerun(_)
+            | Command::Schema(_)
+            | Command::SelfUpdate(_) => {
+                unreachable!("handled earlier")
+            }
+        }
+    };
+
+    if cli.trim_whitespace
+        && let Some(results) = result_list_mut(&mut payload)
+    {
+        output::trim_whitespace(results);
+    }
+    if !cli.result_filter.is_empty()
+        && let Some(results) = result_list_mut(&mut payload)
+    {
+        let before = results.len();
+        results.retain(|result| {
+            cli.result_filter
+                .iter()
+                .all(|filter| filter.matches(result))
+        });
+        if api.verbose >= 1 {
+            eprintln!(
+                "filtered out {} results (--result-filter)",
+                before - results.len()
+            );
+        }
+    }
+    if cli.dedupe
+        && let Some(results) = result_list_mut(&mut payload)
+    {
+        let removed = dedupe_results(results);
+        if api.verbose >= 1 {
+            eprintln!("removed {removed} duplicate results (--dedupe)");
+        }
+    }
+    if let Some(field) = &cli.sort_by
+        && let Some(results) = result_list_mut(&mut payload)
+    {
+        sort_results(results, field, sort_order(cli, field));
+    }
+    if let Some(max) = cli.max_results {
+        cap_results(&mut payload, max, api.verbose);
+    }
+    if cli.prettify_dates {
+        let format = cli
+            .date_format
+            .as_deref()
+            .unwrap_or(dates::DEFAULT_DATE_FORMAT);
+        dates::validate_date_format(format)?;
+        dates::prettify_dates(&mut payload, format);
+    }
+    if cli.flatten {
+        if let Some(results) = result_list_mut(&mut payload) {
+            for result in results {
+                *result = output::flatten(result, cli.flatten_arrays);
+            }
+        } else if payload.is_object() {
+            payload = output::flatten(&payload, cli.flatten_arrays);
+        }
+    }
+    if cli.watch_diff {
+        let current = result_identity(&payload);
+        if previous.as_ref() == Some(&current) {
+            return Ok(0);
+        }
+        *previous = Some(current);
+    }
+    // Readable JSON on a terminal, compact in pipes and --output files, unless
+    // --pretty/--no-pretty says otherwise.
+    let pretty = match (cli.pretty, cli.no_pretty) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => cli.output.is_none() && io::stdout().is_terminal(),
+    };
+    let mut opts = OutputOptions {
+        format: cli.format,
+        pretty,
+        raw: api.raw,
+        template: cli.output_template.clone(),
+        export_prefix: cli.export.then(|| cli.export_prefix.clone()),
+        fields: cli.fields.clone(),
+        domains_summary: cli.domains_summary,
+        compact_arrays: cli.compact_arrays,
+        sort_keys: cli.sort_keys,
+        echo_query: None,
+        lines,
     };
-    println!("{output}");
+    if cli.echo_query
+        && lines.is_none()
+        && let Some(query) = label.or_else(|| sent.as_ref().and_then(sent_label))
+    {
+        // JSON output carries the query in a wrapper; anything else gets a
+        // stderr line so the rendered text stays as it was.
+        let wrapped = opts.format == OutputFormat::Json
+            && opts.template.is_none()
+            && opts.export_prefix.is_none()
+            && !opts.domains_summary
+            && !opts.raw
+            && cli.merge_output.is_none()
+            && !cli.append;
+        if wrapped {
+            opts.echo_query = Some(query);
+        } else {
+            let query = match &query {
+                Value::String(query) => query.clone(),
+                other => other.to_string(),
+            };
+            eprintln!("# query: {query}");
+        }
+    }
+    if let Some(path) = &cli.merge_output {
+        merge_output(cli, path, &payload)?;
+    } else if let Some(path) = &cli.output
+        && cli.append
+    {
+        append_results(path, &payload)?;
+        if cli.summary {
+            println!("{}", output::summary(&payload));
+        }
+    } else if let Some(path) = &cli.output {
+        let output = render_output(&payload, &opts)?;
+        fs::write(path, format!("{output}\n"))
+            .with_context(|| format!("write output {}", path.display()))?;
+        if cli.clipboard || cli.clipboard_only {
+            copy_to_clipboard(&output);
+        }
+        if cli.summary {
+            let summary = if is_diff {
+                diff::summary(&payload)
+            } else {
+                output::summary(&payload)
+            };
+            println!("{summary}");
+        }
+    } else {
+        let output = match cli.truncate {
+            Some(max) => {
+                let mut shown = payload.clone();
+                output::truncate_strings(&mut shown, max);
+                render_output(&shown, &opts)?
+            }
+            None => render_output(&payload, &opts)?,
+        };
+        if cli.watch.is_some() && io::stdout().is_terminal() {
+            print!("\x1b[2J\x1b[H");
+        }
+        let copied = (cli.clipboard || cli.clipboard_only) && copy_to_clipboard(&output);
+        if !(cli.clipboard_only && copied) {
+            if cli.no_newline {
+                print!("{output}");
+                io::stdout().flush().context("flush stdout")?;
+            } else {
+                println!("{output}");
+            }
+        }
+    }
+    if interrupted {
+        return Ok(EXIT_INTERRUPTED);
+    }
+    if fail_on_empty && !has_results(&payload) {
+        return Ok(EXIT_NO_RESULTS);
+    }
+    if partial {
+        return Ok(EXIT_PARTIAL);
+    }
+    Ok(0)
+}
+
+struct OutputOptions {
+    format: OutputFormat,
+    pretty: bool,
+    raw: bool,
+    template: Option<String>,
+    export_prefix: Option<String>,
+    fields: Vec<String>,
+    domains_summary: bool,
+    compact_arrays: bool,
+    sort_keys: bool,
+    /// Under `--echo-query`, wrap JSON output as `{"query", "response"}`.
+    echo_query: Option<Value>,
+    /// JSON output as batch NDJSON lines.
+    lines: Option<batch::Lines>,
+}
+
+fn render_output(payload: &Value, opts: &OutputOptions) -> Result<String> {
+    let sorted;
+    let payload = if opts.sort_keys {
+        sorted = record::canonicalize(payload);
+        &sorted
+    } else {
+        payload
+    };
+    if let (true, Value::String(text)) = (opts.raw, payload) {
+        return Ok(text.clone());
+    }
+    if let Some(prefix) = &opts.export_prefix {
+        return output::render_export(payload, prefix);
+    }
+    if let Some(template) = &opts.template {
+        let results = result_list(payload)
+            .context("--output-template needs a response with a results array")?;
+        return Ok(output::render_template(template, results));
+    }
+    if opts.domains_summary {
+        let results = result_list(payload)
+            .context("--domains-summary needs a response with a results array")?;
+        return Ok(output::domains_summary(results));
+    }
+    let results = result_list(payload);
+    if opts.format == OutputFormat::Csv {
+        let results = results.context("--format csv needs a response with a results array")?;
+        return Ok(output::render_csv(results, &opts.fields));
+    }
+    if opts.format == OutputFormat::Markdown {
+        if let Some(markdown) = output::answer_markdown(payload) {
+            return Ok(markdown);
+        }
+        if let Some(results) = results {
+            return Ok(output::results_markdown(results));
+        }
+    }
+    if let Some(lines) = opts.lines
+        && opts.format == OutputFormat::Json
+    {
+        return Ok(batch::render_lines(payload, lines, &opts.fields));
+    }
+    let projected;
+    let payload = match results {
+        Some(results) if !opts.fields.is_empty() => {
+            let fields = Value::Array(output::project_fields(results, &opts.fields));
+            projected = match payload {
+                Value::Array(_) => fields,
+                _ => {
+                    let mut copy = payload.clone();
+                    copy["results"] = fields;
+                    copy
+                }
+            };
+            &projected
+        }
+        _ => payload,
+    };
+    let wrapped;
+    let payload = match &opts.echo_query {
+        Some(query) => {
+            wrapped = json!({ "query": query, "response": payload });
+            &wrapped
+        }
+        None => payload,
+    };
+    let output = if opts.pretty && opts.compact_arrays {
+        pretty::to_string(payload)?
+    } else if opts.pretty {
+        serde_json::to_string_pretty(payload)?
+    } else {
+        serde_json::to_string(payload)?
+    };
+    Ok(output)
+}
+
+/// Where `--stream-results` writes its lines: `--output` (appended to under
+/// `--append`, locked like `append_results`) or stdout. Flags that need the
+/// whole result set, or that shape a single rendered document, are refused.
+fn stream_writer(cli: &Cli) -> Result<Box<dyn Write>> {
+    let unsupported = [
+        ("--format", cli.format != OutputFormat::Json),
+        ("--output-template", cli.output_template.is_some()),
+        ("--export", cli.export),
+        ("--domains-summary", cli.domains_summary),
+        ("--merge-output", cli.merge_output.is_some()),
+        ("--dedupe", cli.dedupe),
+        ("--sort-by", cli.sort_by.is_some()),
+        ("--result-filter", !cli.result_filter.is_empty()),
+        ("--trim-whitespace", cli.trim_whitespace),
+        ("--flatten", cli.flatten),
+        ("--prettify-dates", cli.prettify_dates),
+        ("--truncate", cli.truncate.is_some()),
+        ("--clipboard", cli.clipboard || cli.clipboard_only),
+        ("--summary", cli.summary),
+        ("--echo-query", cli.echo_query),
+    ];
+    if let Some((flag, _)) = unsupported.iter().find(|(_, set)| *set) {
+        return Err(anyhow!("{flag} cannot be used with --stream-results"));
+    }
+    let Some(path) = &cli.output else {
+        return Ok(Box::new(io::stdout()));
+    };
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(cli.append)
+        .truncate(!cli.append)
+        .open(path)
+        .with_context(|| format!("open output {}", path.display()))?;
+    if cli.append {
+        file.lock()
+            .with_context(|| format!("lock output {}", path.display()))?;
+    }
+    Ok(Box::new(file))
+}
+
+/// Unions the response's results into the results file at `path` (created
+/// if missing) and writes the combined set back, sorted by `--sort-by`.
+fn merge_output(cli: &Cli, path: &Path, payload: &Value) -> Result<()> {
+    let new =
+        result_list(payload).context("--merge-output needs a response with a results array")?;
+    let old = if path.exists() {
+        diff::load_results(path)?
+    } else {
+        Vec::new()
+    };
+    let (mut merged, added) = diff::union(old, new);
+    if let Some(field) = &cli.sort_by {
+        sort_results(&mut merged, field, sort_order(cli, field));
+    }
+    let total = merged.len();
+    let text = serde_json::to_string(&json!({ "results": merged }))?;
+    fs::write(path, format!("{text}\n"))
+        .with_context(|| format!("write merged output {}", path.display()))?;
+    eprintln!(
+        "merged {added} new results into {} ({total} total)",
+        path.display()
+    );
     Ok(())
 }
 
-fn handle_mcp(cmd: &McpCommand) -> Result<()> {
-    match cmd {
-        McpCommand::Tools => {
-            for tool in MCP_TOOLS {
-                println!("{tool}");
-            }
+/// Appends each result (or the whole response when it has no results) as an
+/// NDJSON line. The file is locked while writing so concurrent invocations do
+/// not interleave lines.
+fn append_results(path: &Path, payload: &Value) -> Result<()> {
+    let lines: Vec<String> = match result_list(payload) {
+        Some(results) => results.iter().map(Value::to_string).collect(),
+        None => vec![payload.to_string()],
+    };
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("open output {}", path.display()))?;
+    file.lock()
+        .with_context(|| format!("lock output {}", path.display()))?;
+    let mut text = lines.join("\n");
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    file.write_all(text.as_bytes())
+        .with_context(|| format!("append output {}", path.display()))
+}
+
+fn copy_to_clipboard(text: &str) -> bool {
+    match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text)) {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("warning: clipboard unavailable: {err}");
+            false
+        }
+    }
+}
+
+fn project_results(payload: &mut Value, fields: &[&str]) {
+    let Some(results) = payload.get_mut("results").and_then(Value::as_array_mut) else {
+        return;
+    };
+    for result in results {
+        project_result(result, fields);
+    }
+}
+
+fn project_result(result: &mut Value, fields: &[&str]) {
+    if let Value::Object(map) = result {
+        map.retain(|key, _| fields.contains(&key.as_str()));
+    }
+}
+
+fn cap_results(payload: &mut Value, max: usize, verbose: u8) {
+    let Some(results) = result_list_mut(payload) else {
+        return;
+    };
+    if results.len() > max {
+        if verbose >= 1 {
+            eprintln!(
+                "truncated results from {} to {max} (--max-results)",
+                results.len()
+            );
+        }
+        results.truncate(max);
+    }
+}
+
+/// `--sort-order`, defaulting to A-Z for titles and best-first (descending)
+/// for scores, dates and everything else.
+fn sort_order(cli: &Cli, field: &str) -> SortOrder {
+    let default = if field == "title" {
+        SortOrder::Asc
+    } else {
+        SortOrder::Desc
+    };
+    cli.sort_order.unwrap_or(default)
+}
+
+/// Stable sort by `field` (a dotted path): numbers numerically, RFC 3339
+/// dates chronologically, other strings lexicographically. Results missing
+/// the field go last in either order.
+fn sort_results(results: &mut [Value], field: &str, order: SortOrder) {
+    fn key<'a>(result: &'a Value, field: &str) -> Option<&'a Value> {
+        output::resolve_path(result, field).filter(|value| !value.is_null())
+    }
+    results.sort_by(|a, b| match (key(a, field), key(b, field)) {
+        (Some(a), Some(b)) => match order {
+            SortOrder::Asc => compare_values(a, b),
+            SortOrder::Desc => compare_values(b, a),
+        },
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+}
+
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => {
+            let (a, b) = (a.as_f64().unwrap_or(0.0), b.as_f64().unwrap_or(0.0));
+            a.total_cmp(&b)
+        }
+        (Value::String(a), Value::String(b)) => {
+            match (
+                DateTime::parse_from_rfc3339(a),
+                DateTime::parse_from_rfc3339(b),
+            ) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                _ => a.cmp(b),
+            }
+        }
+        // Mixed kinds: numbers before strings before anything else.
+        _ => kind_rank(a).cmp(&kind_rank(b)),
+    }
+}
+
+fn kind_rank(value: &Value) -> u8 {
+    match value {
+        Value::Number(_) => 0,
+        Value::String(_) => 1,
+        _ => 2,
+    }
+}
+
+/// What `--watch-diff` compares between runs: result ids (or urls) when the
+/// response has results, otherwise the whole response.
+fn result_identity(payload: &Value) -> Value {
+    match result_list(payload) {
+        Some(results) => results
+            .iter()
+            .map(|result| {
+                result
+                    .get("id")
+                    .or_else(|| result.get("url"))
+                    .cloned()
+                    .unwrap_or_else(|| result.clone())
+            })
+            .collect(),
+        None => payload.clone(),
+    }
+}
+
+fn parse_score(raw: &str) -> Result<f64, String> {
+    match raw.parse::<f64>() {
+        Ok(score) if (0.0..=1.0).contains(&score) => Ok(score),
+        _ => Err(format!("{raw:?} is not a score between 0 and 1")),
+    }
+}
+
+/// Removes results whose `score` is below `min` (unscored results are kept)
+/// and returns how many were dropped.
+fn drop_below_score(results: &mut Vec<Value>, min: f64) -> usize {
+    let before = results.len();
+    results.retain(|result| {
+        result
+            .get("score")
+            .and_then(Value::as_f64)
+            .is_none_or(|score| score >= min)
+    });
+    before - results.len()
+}
+
+/// What `--echo-query` shows for a command: its query, url, urls,
+/// instructions, task id or saved query name, when given as a flag.
+fn request_label(command: &Command) -> Option<Value> {
+    let text = match command {
+        Command::Search(args) => args.query.clone(),
+        Command::Answer(args) => args.query.clone(),
+        Command::Context(args) => args.query.clone(),
+        Command::Diff(args) => args.query.clone(),
+        Command::Bundle(args) => Some(args.query.clone()),
+        Command::FindSimilar(args) => args.url.clone(),
+        Command::Run(args) => Some(args.name.clone()),
+        Command::Contents(args) if !args.urls.is_empty() => {
+            return Some(json!(args.urls));
+        }
+        Command::Research(args) => match &args.command {
+            ResearchCommand::Start(args) => args.instructions.clone(),
+            ResearchCommand::Check(args) => args.task_id.clone(),
+        },
+        _ => None,
+    };
+    text.map(Value::String)
+}
+
+/// The same label read from the request body that was sent, for queries
+/// that came from `--body`/`--body-file`.
+fn sent_label(body: &Value) -> Option<Value> {
+    ["query", "instructions", "url", "urls", "ids"]
+        .iter()
+        .find_map(|key| body.get(key).cloned())
+}
+
+fn has_results(payload: &Value) -> bool {
+    result_list(payload).is_some_and(|results| !results.is_empty())
+}
+
+fn result_list(payload: &Value) -> Option<&Vec<Value>> {
+    match payload {
+        Value::Array(items) => Some(items),
+        _ => payload.get("results").and_then(Value::as_array),
+    }
+}
+
+fn result_list_mut(payload: &mut Value) -> Option<&mut Vec<Value>> {
+    match payload {
+        Value::Array(items) => Some(items),
+        _ => payload.get_mut("results").and_then(Value::as_array_mut),
+    }
+}
+
+/// Streamed answers come back as server-sent events; turn each `data:` line
+/// into one element of a JSON array.
+fn sse_events(payload: &Value) -> Value {
+    let Some(text) = payload.get("raw").and_then(Value::as_str) else {
+        return payload.clone();
+    };
+    text.lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(str::trim)
+        .filter(|data| !data.is_empty() && *data != "[DONE]")
+        .map(|data| serde_json::from_str(data).unwrap_or_else(|_| json!(data)))
+        .collect()
+}
+
+fn citations(payload: &Value) -> Value {
+    let citations = payload
+        .get("citations")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    citations
+        .iter()
+        .map(|citation| {
+            json!({
+                "url": citation.get("url").cloned().unwrap_or(Value::Null),
+                "title": citation.get("title").cloned().unwrap_or(Value::Null),
+                "snippet": citation.get("text").cloned().unwrap_or(Value::Null),
+            })
+        })
+        .collect()
+}
+
+fn apply_default_command(mut args: Vec<OsString>, default: Option<&str>) -> Result<Vec<OsString>> {
+    let Some(default) = default else {
+        return Ok(args);
+    };
+    let input_flag = DEFAULT_COMMANDS
+        .iter()
+        .find(|(name, _)| *name == default)
+        .map(|(_, flag)| *flag)
+        .ok_or_else(|| {
+            let known: Vec<&str> = DEFAULT_COMMANDS.iter().map(|(name, _)| *name).collect();
+            anyhow!(
+                "config default_command {default:?} is not one of: {}",
+                known.join(", ")
+            )
+        })?;
+    let cmd = Cli::command();
+    let mut idx = 1;
+    while idx < args.len() {
+        let Some(arg) = args[idx].to_str() else {
+            return Ok(args);
+        };
+        if arg == "--" {
+            return Ok(args);
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            let takes_value = !long.contains('=')
+                && cmd
+                    .get_arguments()
+                    .find(|a| a.get_long() == Some(long))
+                    .is_some_and(|a| a.get_action().takes_values());
+            idx += if takes_value { 2 } else { 1 };
+            continue;
+        }
+        if let Some(short) = arg.strip_prefix('-').filter(|s| !s.is_empty()) {
+            let takes_value = short.len() == 1
+                && cmd
+                    .get_arguments()
+                    .find(|a| a.get_short().map(String::from).as_deref() == Some(short))
+                    .is_some_and(|a| a.get_action().takes_values());
+            idx += if takes_value { 2 } else { 1 };
+            continue;
+        }
+        let known = arg == "help"
+            || cmd
+                .get_subcommands()
+                .any(|sub| sub.get_name() == arg || sub.get_all_aliases().any(|a| a == arg));
+        if !known {
+            args.splice(
+                idx..idx,
+                [OsString::from(default), OsString::from(input_flag)],
+            );
+        }
+        return Ok(args);
+    }
+    Ok(args)
+}
+
+/// What `--config-print` shows: the values a request would use, key redacted.
+fn effective_config(cli: &Cli, env: &Env, profile: &Profile, profile_name: Option<&str>) -> Value {
+    let key = resolve_api_key(cli, env, profile);
+    let key_count = key.as_ref().map_or(0, |(key, _)| {
+        key_pool(key.clone(), profile, cli.key_rotation.is_some()).len()
+    });
+    let (api_base, api_base_source) = resolve_api_base(cli, env, profile);
+    let (timeout, timeout_source) = match (cli.timeout, profile.timeout) {
+        (Some(timeout), _) => (timeout, "--timeout"),
+        (None, Some(timeout)) => (timeout, "config file"),
+        (None, None) => (30, "default"),
+    };
+    let pretty = if cli.pretty {
+        json!(true)
+    } else if cli.no_pretty {
+        json!(false)
+    } else {
+        json!("auto")
+    };
+    json!({
+        "config_file": config::config_path().filter(|path| path.exists()),
+        "profile": profile_name,
+        "api_key": key.as_ref().map(|(key, _)| redact_key(key)),
+        "api_key_source": key.map(|(_, source)| source),
+        "api_base": api_base.trim_end_matches('/'),
+        "api_base_source": api_base_source,
+        "base_path": cli.base_path,
+        "timeout": timeout,
+        "timeout_source": timeout_source,
+        "answer_model": profile.answer_model,
+        "contents": profile.contents.fields(),
+        "pretty": pretty,
+        "format": cli.format.to_possible_value().map(|value| value.get_name().to_string()),
+        "retries": cli.retries,
+        "retry_on": cli.retry_on.as_ref().map_or_else(
+            || retry::StatusSet::default().to_string(),
+            ToString::to_string
+        ),
+        "circuit_breaker_threshold": cli.circuit_breaker_threshold,
+        "concurrency": cli.concurrency,
+        "delay_ms": cli.delay,
+        "body_merge": cli.body_merge.to_possible_value().map(|value| value.get_name().to_string()),
+        "strict": cli.strict,
+        "key_rotation": cli.key_rotation.and_then(|rotation| rotation.to_possible_value()).map(|value| value.get_name().to_string()),
+        "api_keys": key_count,
+    })
+}
+
+fn answer_model(profile: &Profile) -> Result<Option<AnswerModel>> {
+    let Some(name) = &profile.answer_model else {
+        return Ok(None);
+    };
+    AnswerModel::from_str(name, false).map(Some).map_err(|_| {
+        let known: Vec<String> = AnswerModel::value_variants()
+            .iter()
+            .map(|model| model.name())
+            .collect();
+        anyhow!(
+            "answer_model {name:?} in config is not one of {}",
+            known.join(", ")
+        )
+    })
+}
+
+/// The resolved key, followed under `--key-rotation` by the profile's
+/// `api_keys` it does not already include.
+fn key_pool(key: String, profile: &Profile, rotating: bool) -> Vec<String> {
+    let mut keys = vec![key];
+    if rotating {
+        for extra in &profile.api_keys {
+            if !keys.contains(extra) {
+                keys.push(extra.clone());
+            }
+        }
+    }
+    keys
+}
+
+fn redact_key(key: &str) -> String {
+    let len = key.chars().count();
+    if len <= 8 {
+        return "****".to_string();
+    }
+    format!("****{}", key.chars().skip(len - 4).collect::<String>())
+}
+
+/// Flag > environment > config profile, with the name of the source that won.
+fn resolve_api_key(cli: &Cli, env: &Env, profile: &Profile) -> Option<(String, &'static str)> {
+    if let Some(key) = &cli.api_key {
+        return Some((key.clone(), "--api-key"));
+    }
+    if let Some(key) = env.var("EXA_API_KEY") {
+        return Some((key, "EXA_API_KEY"));
+    }
+    profile
+        .api_key
+        .clone()
+        .or_else(|| profile.api_keys.first().cloned())
+        .map(|key| (key, "config file"))
+}
+
+fn resolve_api_base(cli: &Cli, env: &Env, profile: &Profile) -> (String, &'static str) {
+    if let Some(base) = &cli.api_base {
+        return (base.clone(), "--api-base");
+    }
+    if let Some(base) = env.var("EXA_API_BASE") {
+        return (base, "EXA_API_BASE");
+    }
+    match &profile.api_base {
+        Some(base) => (base.clone(), "config file"),
+        None => (DEFAULT_API_BASE.to_string(), "default"),
+    }
+}
+
+fn build_client(cli: &Cli, timeout: u64) -> Result<Client> {
+    let mut builder = Client::builder()
+        .timeout(Duration::from_secs(timeout))
+        .pool_idle_timeout(Duration::from_secs(cli.pool_idle_timeout));
+    if let Some(max_idle) = cli.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max_idle);
+    }
+    if let (Some(cert), Some(key)) = (&cli.client_cert, &cli.client_key) {
+        let mut pem =
+            fs::read(cert).with_context(|| format!("read client cert {}", cert.display()))?;
+        pem.push(b'\n');
+        pem.extend(fs::read(key).with_context(|| format!("read client key {}", key.display()))?);
+        let identity = reqwest::Identity::from_pem(&pem).with_context(|| {
+            format!(
+                "client cert {} and key {} are not a valid PEM identity",
+                cert.display(),
+                key.display()
+            )
+        })?;
+        builder = builder.use_rustls_tls().identity(identity);
+    }
+    if let Some(ca) = &cli.ca_cert {
+        let pem = fs::read(ca).with_context(|| format!("read ca cert {}", ca.display()))?;
+        let cert = reqwest::Certificate::from_pem(&pem)
+            .with_context(|| format!("ca cert {} is not a valid PEM certificate", ca.display()))?;
+        builder = builder.add_root_certificate(cert);
+    }
+    if cli.insecure {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    if cli.http1 {
+        builder = builder.http1_only();
+    }
+    builder.build().context("build http client")
+}
+
+fn handle_mcp(cmd: &McpCommand) -> Result<()> {
+    match cmd {
+        McpCommand::Tools => {
+            for tool in MCP_TOOLS {
+                println!("{tool}");
+            }
+        }
+        McpCommand::Url(args) => {
+            let url = match args.tools.as_deref() {
+                None => MCP_BASE.to_string(),
+                Some("all") => format!("{MCP_BASE}?tools={}", MCP_TOOLS.join(",")),
+                Some(list) => {
+                    let trimmed = list.trim().trim_matches(',');
+                    if trimmed.is_empty() {
+                        return Err(anyhow!("tools list is empty"));
+                    }
+                    format!("{MCP_BASE}?tools={trimmed}")
+                }
+            };
+            println!("{url}");
+        }
+    }
+    Ok(())
+}
+
+/// The `--bodies-file` of a command, with the endpoint path and schema name
+/// its lines are sent to.
+fn bodies_file(command: &Command) -> Option<(&Path, &'static str, &'static str)> {
+    let (body, endpoint, name) = match command {
+        Command::Search(args) => (&args.body, "/search", "search"),
+        Command::Contents(args) => (&args.body, "/contents", "contents"),
+        Command::FindSimilar(args) => (&args.body, "/findSimilar", "find-similar"),
+        Command::Answer(args) => (&args.body, "/answer", "answer"),
+        Command::Context(args) => (&args.body, "/context", "context"),
+        Command::Research(ResearchArgs {
+            command: ResearchCommand::Start(args),
+        }) => (&args.body, "/research/v0/tasks", "research"),
+        _ => return None,
+    };
+    Some((body.bodies_file.as_deref()?, endpoint, name))
+}
+
+/// Command flags that a `--bodies-file` run would ignore, since each line is
+/// sent as it is: the flags that feed the body and the per-command options
+/// applied around the single request.
+fn bodies_file_ignored(command: &Command) -> Vec<&'static str> {
+    let dates = |dates: &DateArgs| {
+        [
+            ("--since", dates.since.is_some()),
+            ("--until", dates.until.is_some()),
+        ]
+    };
+    let domains = |domains: &DomainArgs| {
+        [
+            ("--include-domains", !domains.include_domains.is_empty()),
+            (
+                "--include-domains-file",
+                domains.include_domains_file.is_some(),
+            ),
+            ("--exclude-domains", !domains.exclude_domains.is_empty()),
+            (
+                "--exclude-domains-file",
+                domains.exclude_domains_file.is_some(),
+            ),
+        ]
+    };
+    let flags: Vec<(&'static str, bool)> = match command {
+        Command::Search(args) => [
+            ("--query", args.query.is_some()),
+            ("--queries-file", args.queries_file.is_some()),
+            ("--num-results", args.num_results.is_some()),
+            ("--summary-only", args.summary_only),
+            ("--country", args.country.is_some()),
+            ("--min-score", args.min_score.is_some()),
+            ("--save-query", args.save_query.is_some()),
+            ("--contents-from-search", args.contents_from_search),
+            ("--preview-first", args.preview_first),
+        ]
+        .into_iter()
+        .chain(dates(&args.dates))
+        .chain(domains(&args.domains))
+        .collect(),
+        Command::Contents(args) => vec![
+            ("--urls", !args.urls.is_empty()),
+            ("--ids", !args.ids.is_empty()),
+            ("--metadata-only", args.metadata_only),
+            ("--extras-links", args.extras_links.is_some()),
+            ("--extras-images", args.extras_images.is_some()),
+            ("--batch-size", args.batch_size.is_some()),
+            ("--fail-on-partial", args.fail_on_partial),
+            ("--as-markdown", args.as_markdown),
+            ("--diff-text", args.diff_text),
+            ("--skip-unchanged", args.skip_unchanged),
+        ],
+        Command::FindSimilar(args) => [
+            ("--url", args.url.is_some()),
+            ("--category", args.category.is_some()),
+        ]
+        .into_iter()
+        .chain(dates(&args.dates))
+        .chain(domains(&args.domains))
+        .collect(),
+        Command::Answer(args) => vec![
+            ("--query", args.query.is_some()),
+            ("--citations-only", args.citations_only),
+            ("--stream", args.stream),
+            ("--no-stream", args.no_stream),
+            ("--model", args.model.is_some()),
+            ("--system-prompt", args.system_prompt.is_some()),
+            ("--system-prompt-file", args.system_prompt_file.is_some()),
+        ],
+        Command::Context(args) => vec![("--query", args.query.is_some())],
+        Command::Research(ResearchArgs {
+            command: ResearchCommand::Start(args),
+        }) => vec![
+            ("--instructions", args.instructions.is_some()),
+            ("--idempotency-key", args.idempotency_key.is_some()),
+        ],
+        _ => Vec::new(),
+    };
+    flags
+        .into_iter()
+        .filter_map(|(flag, set)| set.then_some(flag))
+        .collect()
+}
+
+/// Resolves the inputs read from stdin: a `--query -` (trailing newlines
+/// trimmed) and a `--body-stdin` JSON object, which then merges with flags
+/// like `--body` does.
+fn read_stdin_inputs(command: &mut Command, stdin: &mut dyn Read) -> Result<()> {
+    let (query, body) = match command {
+        Command::Search(args) => (Some(&mut args.query), &mut args.body),
+        Command::Answer(args) => (Some(&mut args.query), &mut args.body),
+        Command::Context(args) => (Some(&mut args.query), &mut args.body),
+        Command::Contents(args) => (None, &mut args.body),
+        Command::FindSimilar(args) => (None, &mut args.body),
+        Command::Research(ResearchArgs {
+            command: ResearchCommand::Start(args),
+        }) => (None, &mut args.body),
+        _ => return Ok(()),
+    };
+    let query = query.filter(|query| query.as_deref() == Some("-"));
+    if query.is_some() && body.body_stdin {
+        return Err(anyhow!("--query - and --body-stdin cannot both read stdin"));
+    }
+    if let Some(query) = query {
+        let text = read_stdin(stdin, "query")?;
+        *query = Some(text.trim_end_matches(['\n', '\r']).to_string());
+    }
+    if body.body_stdin {
+        body.body = Some(read_stdin(stdin, "body")?);
+    }
+    Ok(())
+}
+
+fn read_stdin(stdin: &mut dyn Read, what: &str) -> Result<String> {
+    let mut text = String::new();
+    stdin
+        .read_to_string(&mut text)
+        .with_context(|| format!("read {what} from stdin"))?;
+    if text.trim().is_empty() {
+        return Err(anyhow!("read an empty {what} from stdin"));
+    }
+    Ok(text)
+}
+
+fn load_body(args: &BodyArgs) -> Result<Map<String, Value>> {
+    let Some(raw) = inline_or_file("body", &args.body, &args.body_file)? else {
+        return Ok(Map::new());
+    };
+    let json5_file = args
+        .body_file
+        .as_ref()
+        .is_some_and(|path| path.extension().is_some_and(|ext| ext == "json5"));
+    let format = args.body_format.unwrap_or(if json5_file {
+        BodyFormat::Json5
+    } else {
+        BodyFormat::Json
+    });
+    let value: Value = match format {
+        BodyFormat::Json => serde_json::from_str(&raw).context("parse body json")?,
+        BodyFormat::Json5 => json5::from_str(&raw).context("parse body json5")?,
+    };
+    match value {
+        Value::Object(map) => Ok(map),
+        Value::Null => Ok(Map::new()),
+        _ => Err(anyhow!("--body must be a JSON object")),
+    }
+}
+
+/// The value of `--NAME` or the contents of `--NAME-file`, which are
+/// mutually exclusive.
+fn inline_or_file(
+    name: &str,
+    inline: &Option<String>,
+    path: &Option<PathBuf>,
+) -> Result<Option<String>> {
+    match (inline, path) {
+        (Some(_), Some(_)) => Err(anyhow!("use only one of --{name} or --{name}-file")),
+        (Some(value), None) => Ok(Some(value.clone())),
+        (None, Some(path)) => fs::read_to_string(path)
+            .map(Some)
+            .with_context(|| format!("read {name} file {}", path.display())),
+        (None, None) => Ok(None),
+    }
+}
+
+fn is_json_mime(mime: &str) -> bool {
+    let essence = mime
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    essence == "application/json" || essence.ends_with("+json")
+}
+
+fn parse_api_base(api_base: &str) -> Result<Url> {
+    let url =
+        Url::parse(api_base).map_err(|err| anyhow!("invalid api base {api_base:?}: {err}"))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(anyhow!(
+            "invalid api base {api_base:?}: scheme must be http or https, got {:?}",
+            url.scheme()
+        ));
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(anyhow!("invalid api base {api_base:?}: missing host"));
+    }
+    Ok(url)
+}
+
+fn ensure_insecure_allowed(base_url: &Url) -> Result<()> {
+    let host = base_url.host_str().map(str::to_ascii_lowercase);
+    if let Some(host) = host
+        && (host == "exa.ai" || host.ends_with(".exa.ai"))
+    {
+        return Err(anyhow!("--insecure is not allowed against {host}"));
+    }
+    Ok(())
+}
+
+fn normalize_base_path(path: &str) -> String {
+    let trimmed = path.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("/{trimmed}")
+    }
+}
+
+/// Sets a field given by a flag on a request body loaded from `--body`,
+/// resolving a clash with an existing key by `--body-merge`.
+fn merge_flag(
+    body: &mut Map<String, Value>,
+    key: &str,
+    value: Value,
+    merge: BodyMerge,
+) -> Result<()> {
+    match (merge, body.get(key)) {
+        (BodyMerge::BodyWins, Some(_)) => {}
+        (BodyMerge::ErrorOnConflict, Some(existing)) if *existing != value => {
+            return Err(anyhow!("{key} is set by both a flag and the body"));
         }
-        McpCommand::Url(args) => {
-            let url = match args.tools.as_deref() {
-                None => MCP_BASE.to_string(),
-                Some("all") => format!("{MCP_BASE}?tools={}", MCP_TOOLS.join(",")),
-                Some(list) => {
-                    let trimmed = list.trim().trim_matches(',');
-                    if trimmed.is_empty() {
-                        return Err(anyhow!("tools list is empty"));
-                    }
-                    format!("{MCP_BASE}?tools={trimmed}")
-                }
-            };
-            println!("{url}");
+        _ => {
+            body.insert(key.to_string(), value);
         }
     }
     Ok(())
 }
 
-fn load_body(args: &BodyArgs) -> Result<Map<String, Value>> {
-    if args.body.is_some() && args.body_file.is_some() {
-        return Err(anyhow!("use only one of --body or --body-file"));
-    }
-    let raw = match (&args.body, &args.body_file) {
-        (Some(body), None) => Some(body.clone()),
-        (None, Some(path)) => Some(
-            fs::read_to_string(path)
-                .with_context(|| format!("read body file {}", path.display()))?,
-        ),
-        (None, None) => None,
-        _ => None,
+/// Runs `body` with `numResults: 1` and prints that result to stderr. On a
+/// terminal, asks whether to run the full search unless `yes`; returns
+/// whether to go on.
+async fn preview_search(api: &Api, body: &Map<String, Value>, yes: bool) -> Result<bool> {
+    let mut preview = body.clone();
+    preview.insert("numResults".to_string(), json!(1));
+    let payload = exa_post(api, "/search", Value::Object(preview)).await?;
+    match result_list(&payload).and_then(|results| results.first()) {
+        Some(result) => eprintln!("{}", serde_json::to_string_pretty(result)?),
+        None => eprintln!("preview: no results"),
+    }
+    if yes || !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        return Ok(true);
+    }
+    eprint!("run the full search? [y/N] ");
+    io::stderr().flush().context("flush stderr")?;
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .context("read answer from stdin")?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Adds the profile's `[contents]` options that neither a flag nor the body
+/// set. They go under `key` (`contents` for search and find-similar), or at
+/// the top level for the contents endpoint when `key` is empty.
+fn apply_content_defaults(api: &Api, body: &mut Map<String, Value>, key: &str) -> Result<()> {
+    if api.content_defaults.is_empty() {
+        return Ok(());
+    }
+    let target = if key.is_empty() {
+        body
+    } else {
+        match body.entry(key).or_insert_with(|| Value::Object(Map::new())) {
+            Value::Object(contents) => contents,
+            _ => return Err(anyhow!("{key} in body must be an object")),
+        }
     };
-    let Some(raw) = raw else {
-        return Ok(Map::new());
+    for (field, value) in &api.content_defaults {
+        target.entry(field).or_insert_with(|| value.clone());
+    }
+    Ok(())
+}
+
+/// `--extras-links`/`--extras-images` as `extras.links`/`extras.imageLinks`,
+/// merged into an `extras` object from the body. Nothing is added when
+/// neither flag is set.
+fn apply_extras(
+    args: &ContentsArgs,
+    body: &mut Map<String, Value>,
+    merge: BodyMerge,
+) -> Result<()> {
+    let counts = [
+        ("links", args.extras_links),
+        ("imageLinks", args.extras_images),
+    ];
+    if counts.iter().all(|(_, count)| count.is_none()) {
+        return Ok(());
+    }
+    let extras = body
+        .entry("extras")
+        .or_insert_with(|| Value::Object(Map::new()));
+    let Value::Object(extras) = extras else {
+        return Err(anyhow!("extras in body must be an object"));
     };
-    let value: Value = serde_json::from_str(&raw).context("parse body json")?;
-    match value {
-        Value::Object(map) => Ok(map),
-        Value::Null => Ok(Map::new()),
-        _ => Err(anyhow!("--body must be a JSON object")),
+    for (key, count) in counts {
+        if let Some(count) = count {
+            merge_flag(extras, key, json!(count), merge)?;
+        }
+    }
+    Ok(())
+}
+
+fn apply_dates(args: &DateArgs, body: &mut Map<String, Value>, merge: BodyMerge) -> Result<()> {
+    if let (Some(since), Some(until)) = (args.since, args.until)
+        && since <= until
+    {
+        return Err(anyhow!("--since must be further in the past than --until"));
+    }
+    if let Some(since) = args.since {
+        merge_flag(body, "startPublishedDate", json!(dates::ago(since)), merge)?;
+    }
+    if let Some(until) = args.until {
+        merge_flag(body, "endPublishedDate", json!(dates::ago(until)), merge)?;
+    }
+    Ok(())
+}
+
+fn apply_domains(args: &DomainArgs, body: &mut Map<String, Value>, merge: BodyMerge) -> Result<()> {
+    let lists = [
+        (
+            "includeDomains",
+            &args.include_domains,
+            &args.include_domains_file,
+        ),
+        (
+            "excludeDomains",
+            &args.exclude_domains,
+            &args.exclude_domains_file,
+        ),
+    ];
+    for (key, inline, file) in lists {
+        let mut domains = inline.clone();
+        if let Some(path) = file {
+            domains.extend(read_list_file(path)?);
+        }
+        if let Some(domains) = normalize_list(&domains) {
+            merge_flag(body, key, Value::Array(domains), merge)?;
+        }
     }
+    Ok(())
+}
+
+fn read_list_file(path: &Path) -> Result<Vec<String>> {
+    let raw =
+        fs::read_to_string(path).with_context(|| format!("read list file {}", path.display()))?;
+    Ok(raw
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
 }
 
 fn normalize_list(items: &[String]) -> Option<Vec<Value>> {
+    let mut seen = HashSet::new();
     let values: Vec<Value> = items
         .iter()
         .map(|s| s.trim())
         .filter(|s| !s.is_empty())
+        .filter(|s| seen.insert(*s))
         .map(|s| Value::String(s.to_string()))
         .collect();
     if values.is_empty() {
@@ -327,6 +2610,244 @@ fn normalize_list(items: &[String]) -> Option<Vec<Value>> {
     }
 }
 
+fn dedupe_contents_targets(body: &mut Map<String, Value>) -> usize {
+    let mut seen = HashSet::new();
+    let mut dropped = 0;
+    for key in ["urls", "ids"] {
+        let Some(Value::Array(items)) = body.get_mut(key) else {
+            continue;
+        };
+        items.retain(|item| match item.as_str() {
+            Some(target) if !seen.insert(target.trim().to_string()) => {
+                dropped += 1;
+                false
+            }
+            _ => true,
+        });
+        if items.is_empty() {
+            body.remove(key);
+        }
+    }
+    dropped
+}
+
+/// Fetches contents for search results that came back without the requested
+/// content fields and merges what is returned into them. `options` is the
+/// search body's `contents` object (default: text). Urls that fail to crawl
+/// are listed under `contentsErrors`.
+async fn attach_contents(api: &Api, payload: &mut Value, options: Option<Value>) -> Result<()> {
+    let mut body = match options {
+        Some(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+    let wanted: Vec<String> = CONTENT_FIELDS
+        .iter()
+        .filter(|field| {
+            body.get(**field)
+                .is_some_and(|value| value != &Value::Bool(false))
+        })
+        .map(|field| field.to_string())
+        .collect();
+    let wanted = if wanted.is_empty() {
+        body.insert("text".to_string(), Value::Bool(true));
+        vec!["text".to_string()]
+    } else {
+        wanted
+    };
+    let urls: Vec<Value> = result_list(payload)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter(|result| !wanted.iter().all(|field| result.get(field).is_some()))
+        .filter_map(|result| result.get("url").cloned())
+        .collect();
+    if urls.is_empty() {
+        return Ok(());
+    }
+    body.insert("urls".to_string(), Value::Array(urls));
+    let mut contents = exa_post(api, "/contents", Value::Object(body)).await?;
+    let (_, failed) = split_contents_errors(&mut contents);
+    let fetched: HashMap<&str, &Map<String, Value>> = result_list(&contents)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|result| Some((result.get("url")?.as_str()?, result.as_object()?)))
+        .collect();
+    if let Some(results) = result_list_mut(payload) {
+        for result in results {
+            let url = result.get("url").and_then(Value::as_str).unwrap_or("");
+            let (Some(fields), Some(result)) = (fetched.get(url), result.as_object_mut()) else {
+                continue;
+            };
+            for (key, value) in fields.iter() {
+                result.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+    }
+    if failed > 0
+        && let Some(map) = payload.as_object_mut()
+    {
+        map.insert("contentsErrors".to_string(), contents["errors"].take());
+    }
+    Ok(())
+}
+
+/// Replaces the per-item `statuses` of a contents response with an `errors`
+/// list holding only the failed items. Returns the ok and failed counts.
+fn split_contents_errors(payload: &mut Value) -> (usize, usize) {
+    let Some(map) = payload.as_object_mut() else {
+        return (0, 0);
+    };
+    let errors: Vec<Value> = match map.remove("statuses") {
+        Some(Value::Array(statuses)) => statuses
+            .into_iter()
+            .filter(|status| status.get("status").and_then(Value::as_str) == Some("error"))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let ok = map
+        .get("results")
+        .and_then(Value::as_array)
+        .map_or(0, Vec::len);
+    let failed = errors.len();
+    map.insert("errors".to_string(), Value::Array(errors));
+    (ok, failed)
+}
+
+fn count_duplicate_results(payload: &Value) -> usize {
+    let Some(results) = payload.get("results").and_then(Value::as_array) else {
+        return 0;
+    };
+    let mut seen = HashSet::new();
+    results
+        .iter()
+        .filter_map(|result| result.get("url").and_then(Value::as_str))
+        .filter(|url| !seen.insert(*url))
+        .count()
+}
+
+/// Drops results whose canonical url repeats an earlier one, keeping the
+/// highest-scored copy in the first copy's position. Returns how many went.
+fn dedupe_results(results: &mut Vec<Value>) -> usize {
+    let score = |result: &Value| result.get("score").and_then(Value::as_f64);
+    let before = results.len();
+    let mut kept: Vec<Value> = Vec::with_capacity(results.len());
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for result in results.drain(..) {
+        let Some(url) = result.get("url").and_then(Value::as_str) else {
+            kept.push(result);
+            continue;
+        };
+        match index.get(&canonical_url(url)) {
+            Some(&idx) => {
+                if score(&result) > score(&kept[idx]) {
+                    kept[idx] = result;
+                }
+            }
+            None => {
+                index.insert(canonical_url(url), kept.len());
+                kept.push(result);
+            }
+        }
+    }
+    *results = kept;
+    before - results.len()
+}
+
+/// Url used to spot duplicates: lowercase host, no trailing slash, no
+/// tracking parameters. Unparseable urls compare as written.
+fn canonical_url(url: &str) -> String {
+    let Ok(parsed) = Url::parse(url.trim()) else {
+        return url.trim().to_string();
+    };
+    let Some(host) = parsed.host_str() else {
+        return url.trim().to_string();
+    };
+    let mut out = format!("{}://{}", parsed.scheme(), host.to_ascii_lowercase());
+    if let Some(port) = parsed.port() {
+        out.push_str(&format!(":{port}"));
+    }
+    out.push_str(parsed.path().trim_end_matches('/'));
+    let query: Vec<String> = parsed
+        .query_pairs()
+        .filter(|(key, _)| !key.starts_with("utm_") && !TRACKING_PARAMS.contains(&key.as_ref()))
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    if !query.is_empty() {
+        out.push('?');
+        out.push_str(&query.join("&"));
+    }
+    out
+}
+
+/// `--strict` checks that need only the command line, run before anything
+/// is sent: flags that this command would ignore (result flags on commands
+/// without results, command flags next to `--bodies-file`), and
+/// `--body-merge body-wins`, which lets the body silently override a flag.
+fn check_strict(cli: &Cli, command: &Command) -> Result<()> {
+    if cli.body_merge == BodyMerge::BodyWins {
+        return Err(anyhow!(
+            "--body-merge body-wins cannot be used with --strict"
+        ));
+    }
+    let batch_ignored =
+        bodies_file(command).map_or_else(Vec::new, |_| bodies_file_ignored(command));
+    if !batch_ignored.is_empty() {
+        return Err(anyhow!(
+            "{} has no effect with --bodies-file (--strict)",
+            batch_ignored.join(", ")
+        ));
+    }
+    let has_results = bodies_file(command).is_some()
+        || match command {
+            Command::Search(_) | Command::FindSimilar(_) | Command::Run(_) => true,
+            Command::Contents(args) => !args.as_markdown && !args.diff_text,
+            Command::Answer(args) => args.citations_only,
+            // The replayed command is checked when it runs.
+            Command::Rerun(_) => true,
+            _ => false,
+        };
+    let ignored = [
+        ("--max-results", cli.max_results.is_some()),
+        ("--dedupe", cli.dedupe),
+        ("--sort-by", cli.sort_by.is_some()),
+        ("--result-filter", !cli.result_filter.is_empty()),
+        ("--trim-whitespace", cli.trim_whitespace),
+    ];
+    if !has_results && let Some((flag, _)) = ignored.iter().find(|(_, set)| *set) {
+        return Err(anyhow!(
+            "{flag} has no effect: `exa {}` returns no results (--strict)",
+            command.name()
+        ));
+    }
+    Ok(())
+}
+
+/// Warns about top-level body fields `exa schema` does not list for
+/// `endpoint`; an error under `--strict`.
+fn check_body_fields(cli: &Cli, endpoint: &str, body: &Map<String, Value>) -> Result<()> {
+    let Some(params) = schema::params(endpoint) else {
+        return Ok(());
+    };
+    let unknown: Vec<&str> = body
+        .keys()
+        .map(String::as_str)
+        .filter(|key| !params.iter().any(|param| param.name == *key))
+        .collect();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    let message = format!(
+        "unknown {endpoint} body fields: {} (see `exa schema {endpoint}`)",
+        unknown.join(", ")
+    );
+    if cli.strict {
+        return Err(anyhow!("{message} (--strict)"));
+    }
+    eprintln!("warning: {message}");
+    Ok(())
+}
+
 fn ensure_string_field(body: &Map<String, Value>, key: &str) -> Result<()> {
     let value = body
         .get(key)
@@ -347,38 +2868,745 @@ fn ensure_any_field(body: &Map<String, Value>, keys: &[&str]) -> Result<()> {
     Err(anyhow!("missing one of: {}", keys.join(", ")))
 }
 
-async fn exa_post(client: &Client, base: &str, key: &str, path: &str, body: Value) -> Result<Value> {
-    let url = format!("{base}{path}");
-    let resp = client
-        .post(url)
-        .header(reqwest::header::ACCEPT, "application/json")
-        .header(reqwest::header::CONTENT_TYPE, "application/json")
-        .header("x-api-key", key)
-        .json(&body)
-        .send()
-        .await
-        .context("exa request")?;
-    parse_response(resp).await
+struct Api {
+    client: Client,
+    metrics: Option<Metrics>,
+    /// Client-wide request timeout, reported in timeout errors.
+    timeout: Duration,
+    base: String,
+    keys: Keys,
+    accept: String,
+    raw: bool,
+    /// Append a remediation hint to recognized API errors.
+    explain_errors: bool,
+    /// The profile's `[contents]` options, applied beneath flags and body.
+    content_defaults: Map<String, Value>,
+    /// Off under `--no-fail-on-http-error`: error bodies are returned as the
+    /// response and `http_failed` is set instead.
+    fail_on_http_error: bool,
+    http_failed: AtomicBool,
+    max_body_bytes: usize,
+    max_response_bytes: usize,
+    verbose: u8,
+    log: Option<Logger>,
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    dump: Option<Dumper>,
+    print_curl: bool,
+    /// Header name and secret for `--signing-secret` body signatures.
+    signing: Option<(String, String)>,
+    trace_id: String,
+    delay: Duration,
+    last_request: tokio::sync::Mutex<Option<Instant>>,
+    retries: u32,
+    retry_on: StatusSet,
+    breaker: Breaker,
 }
 
-async fn exa_get(client: &Client, base: &str, key: &str, path: &str) -> Result<Value> {
-    let url = format!("{base}{path}");
-    let resp = client
-        .get(url)
-        .header(reqwest::header::ACCEPT, "application/json")
-        .header("x-api-key", key)
-        .send()
-        .await
-        .context("exa request")?;
-    parse_response(resp).await
+/// An `Api` with default flags against `base`, for unit tests.
+#[cfg(test)]
+fn test_api(base: &str, breaker_threshold: u32) -> Api {
+    Api {
+        client: Client::new(),
+        metrics: None,
+        timeout: Duration::from_secs(5),
+        base: base.to_string(),
+        keys: Keys::new(vec!["test-key".to_string()], None),
+        accept: "application/json".to_string(),
+        raw: false,
+        explain_errors: false,
+        content_defaults: Map::new(),
+        fail_on_http_error: true,
+        http_failed: AtomicBool::new(false),
+        max_body_bytes: usize::MAX,
+        max_response_bytes: usize::MAX,
+        verbose: 0,
+        log: None,
+        record: None,
+        replay: None,
+        dump: None,
+        print_curl: false,
+        signing: None,
+        trace_id: "test".to_string(),
+        delay: Duration::ZERO,
+        last_request: tokio::sync::Mutex::new(None),
+        retries: 0,
+        retry_on: StatusSet::default(),
+        breaker: Breaker::new(breaker_threshold),
+    }
+}
+
+async fn exa_post(api: &Api, path: &str, body: Value) -> Result<Value> {
+    exa_post_with(api, path, body, &RequestOptions::default()).await
 }
 
-async fn parse_response(resp: reqwest::Response) -> Result<Value> {
+/// Per-request extras on top of what every call sends.
+#[derive(Default)]
+struct RequestOptions<'a> {
+    headers: &'a [(&'a str, &'a str)],
+    timeout: Option<Duration>,
+    /// Neither refused by an open circuit breaker nor counted towards it,
+    /// for polls whose failures say nothing about the batch.
+    skip_breaker: bool,
+}
+
+async fn exa_post_with(
+    api: &Api,
+    path: &str,
+    body: Value,
+    opts: &RequestOptions<'_>,
+) -> Result<Value> {
+    let body = serde_json::to_vec(&body).context("encode body json")?;
+    if body.len() > api.max_body_bytes {
+        return Err(anyhow!(
+            "request body is {} bytes, over the --max-body-bytes limit of {}",
+            body.len(),
+            api.max_body_bytes
+        ));
+    }
+    execute(api, Method::POST, path, Some(body), opts).await
+}
+
+async fn exa_get(api: &Api, path: &str) -> Result<Value> {
+    exa_get_with(api, path, &RequestOptions::default()).await
+}
+
+async fn exa_get_with(api: &Api, path: &str, opts: &RequestOptions<'_>) -> Result<Value> {
+    execute(api, Method::GET, path, None, opts).await
+}
+
+async fn execute(
+    api: &Api,
+    method: Method,
+    path: &str,
+    body: Option<Vec<u8>>,
+    opts: &RequestOptions<'_>,
+) -> Result<Value> {
+    if let Some(dir) = &api.replay {
+        let (status, text) = record::load(dir, method.as_str(), path, body.as_deref())?;
+        return parse_response(api, status, text);
+    }
+    let url = format!("{}{path}", api.base);
+    let mut attempt = 0;
+    loop {
+        if !opts.skip_breaker && api.breaker.is_open() {
+            api.breaker.skip();
+            return Err(anyhow!(
+                "circuit breaker open after {} consecutive failures; request skipped",
+                api.breaker.threshold()
+            ));
+        }
+        let Some((key_idx, key)) = api.keys.pick() else {
+            return Err(anyhow!(
+                "all {} API keys were rejected (401/403)",
+                api.keys.len()
+            ));
+        };
+        let mut req = api
+            .client
+            .request(method.clone(), &url)
+            .header(reqwest::header::ACCEPT, &api.accept)
+            .header("x-api-key", key)
+            .header("x-request-id", &api.trace_id);
+        for (name, value) in opts.headers {
+            req = req.header(*name, *value);
+        }
+        if let Some((header, secret)) = &api.signing {
+            req = req.header(
+                header,
+                sign_body(secret, body.as_deref().unwrap_or_default()),
+            );
+        }
+        if let Some(timeout) = opts.timeout {
+            req = req.timeout(timeout);
+        }
+        if let Some(body) = &body {
+            req = req
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone());
+        }
+        let request = req.build().context("build request")?;
+        if api.print_curl && attempt == 0 {
+            eprintln!("{}", dump::curl_command(&request, body.as_deref()));
+        }
+        let dump = match &api.dump {
+            Some(dumper) => Some((dumper, dumper.request(&request, body.as_deref())?)),
+            None => None,
+        };
+        let started = Instant::now();
+        let sent = send(api, request, body.as_deref()).await;
+        if let Some(metrics) = &api.metrics {
+            metrics.request(path, started.elapsed());
+            if sent.is_err() {
+                metrics.failure("network");
+            }
+        }
+        let resp = match sent {
+            Ok(resp) => resp,
+            Err(err) if retry::is_transient_error(&err) => {
+                if !opts.skip_breaker {
+                    api.breaker.record(true);
+                }
+                if attempt < api.retries && (opts.skip_breaker || !api.breaker.is_open()) {
+                    if let Some(metrics) = &api.metrics {
+                        metrics.retry();
+                    }
+                    if api.verbose >= 1 {
+                        eprintln!(
+                            "retrying after {} (attempt {})",
+                            err.root_cause(),
+                            attempt + 1
+                        );
+                    }
+                    tokio::time::sleep(retry::backoff(attempt)).await;
+                    attempt += 1;
+                    continue;
+                }
+                return Err(err);
+            }
+            Err(err) => return Err(err),
+        };
+        let (version, headers) = (resp.version(), resp.headers().clone());
+        let timeout = opts.timeout.unwrap_or(api.timeout);
+        let (status, text) = read_response(resp, api.max_response_bytes, timeout).await?;
+        if let Some((dumper, stem)) = dump {
+            dumper.response(&stem, status, version, &headers, &text)?;
+        }
+        if matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)
+            && api.keys.reject(key_idx)
+        {
+            if api.verbose >= 1 {
+                eprintln!(
+                    "key #{} rejected with {status}; trying another",
+                    key_idx + 1
+                );
+            }
+            continue;
+        }
+        let transient = api.retry_on.contains(status);
+        // A 429 means the host is up but busy: retried, never counted.
+        if !opts.skip_breaker && status != StatusCode::TOO_MANY_REQUESTS {
+            api.breaker.record(transient);
+        }
+        if let Some(metrics) = &api.metrics
+            && !status.is_success()
+        {
+            metrics.failure(status.as_str());
+        }
+        if transient && attempt < api.retries && (opts.skip_breaker || !api.breaker.is_open()) {
+            if let Some(metrics) = &api.metrics {
+                metrics.retry();
+            }
+            if api.verbose >= 1 {
+                eprintln!("retrying after {status} (attempt {})", attempt + 1);
+            }
+            tokio::time::sleep(retry::backoff(attempt)).await;
+            attempt += 1;
+            continue;
+        }
+        if let Some(dir) = &api.record {
+            record::save(dir, method.as_str(), path, body.as_deref(), status, &text)?;
+        }
+        let payload = parse_response(api, status, text)?;
+        if let Some(metrics) = &api.metrics {
+            metrics.results(result_list(&payload).map_or(0, Vec::len));
+        }
+        return Ok(payload);
+    }
+}
+
+fn write_metrics(cli: &Cli, api: &Api) {
+    if let (Some(path), Some(metrics)) = (&cli.metrics_file, &api.metrics)
+        && let Err(err) = metrics.write(path)
+    {
+        eprintln!("warning: {err}");
+    }
+}
+
+/// Lowercase hex HMAC-SHA256 of the exact body bytes sent (empty for GET).
+fn sign_body(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts any key length");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+async fn pace(api: &Api) {
+    if api.delay.is_zero() {
+        return;
+    }
+    let mut last = api.last_request.lock().await;
+    if let Some(last) = *last {
+        tokio::time::sleep_until((last + api.delay).into()).await;
+    }
+    *last = Some(Instant::now());
+}
+
+async fn send(api: &Api, request: Request, body: Option<&[u8]>) -> Result<Response> {
+    pace(api).await;
+    let method = request.method().clone();
+    let url = request.url().to_string();
+    if api.verbose >= 2
+        && let Some(body) = body
+    {
+        eprintln!("> {}", String::from_utf8_lossy(body));
+    }
+    let started = Instant::now();
+    let timeout = request.timeout().copied().unwrap_or(api.timeout);
+    let result = api.client.execute(request).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let mut fields = Map::new();
+    fields.insert("method".to_string(), json!(method.as_str()));
+    fields.insert("url".to_string(), json!(url));
+    fields.insert("latency_ms".to_string(), json!(latency_ms));
+    if let Some(log) = &api.log
+        && log.bodies
+        && let Some(body) = body
+    {
+        fields.insert("body".to_string(), json!(String::from_utf8_lossy(body)));
+    }
+    match &result {
+        Ok(resp) => {
+            let status = resp.status();
+            let version = resp.version();
+            if api.verbose >= 1 {
+                eprintln!("{method} {url} -> {status} {version:?} ({latency_ms}ms)");
+            }
+            fields.insert("status".to_string(), json!(status.as_u16()));
+            fields.insert("version".to_string(), json!(format!("{version:?}")));
+            let level = if status.is_success() { "info" } else { "error" };
+            if let Some(log) = &api.log {
+                log.write(level, "request", fields);
+            }
+        }
+        Err(err) => {
+            if api.verbose >= 1 {
+                eprintln!("{method} {url} -> failed ({latency_ms}ms): {err}");
+            }
+            fields.insert("error".to_string(), json!(err.to_string()));
+            if let Some(log) = &api.log {
+                log.write("error", "request", fields);
+            }
+        }
+    }
+    result.map_err(|err| network::classify(err, timeout, "exa request"))
+}
+
+/// Reads the body chunk by chunk so an oversized response is rejected before
+/// it is fully buffered.
+async fn read_response(
+    mut resp: Response,
+    max_bytes: usize,
+    timeout: Duration,
+) -> Result<(StatusCode, String)> {
     let status = resp.status();
-    let text = resp.text().await.context("exa body")?;
-    let payload = serde_json::from_str(&text).unwrap_or_else(|_| json!({ "raw": text }));
+    let too_large =
+        || anyhow!("response body exceeds the --max-response-bytes limit of {max_bytes} bytes");
+    if resp
+        .content_length()
+        .is_some_and(|len| len > max_bytes as u64)
+    {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = resp
+        .chunk()
+        .await
+        .map_err(|err| network::classify(err, timeout, "exa body"))?
+    {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok((status, String::from_utf8_lossy(&body).into_owned()))
+}
+
+fn parse_response(api: &Api, status: StatusCode, text: String) -> Result<Value> {
+    let payload = if api.raw {
+        Value::String(text)
+    } else {
+        serde_json::from_str(&text).unwrap_or_else(|_| json!({ "raw": text }))
+    };
     if !status.is_success() {
+        let hint = api
+            .explain_errors
+            .then(|| hints::explain(status.as_u16(), &payload))
+            .flatten();
+        if !api.fail_on_http_error {
+            // The body becomes the result; the run still exits non-zero.
+            api.http_failed.store(true, AtomicOrdering::SeqCst);
+            eprintln!("error: exa api failed status={status}");
+            if let Some(hint) = hint {
+                eprintln!("hint: {hint}");
+            }
+            return Ok(payload);
+        }
+        if let Some(hint) = hint {
+            return Err(anyhow!(
+                "exa api failed status={} body={}\nhint: {hint}",
+                status,
+                payload
+            ));
+        }
         return Err(anyhow!("exa api failed status={} body={}", status, payload));
     }
     Ok(payload)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            other => panic!("not an object: {other}"),
+        }
+    }
+
+    #[test]
+    fn contents_targets_dedupe_within_and_across_lists() {
+        let mut body = object(json!({
+            "urls": ["https://a.com", "https://b.com", " https://a.com "],
+            "ids": ["https://b.com", "id-1", "id-1"],
+            "text": true,
+        }));
+        assert_eq!(dedupe_contents_targets(&mut body), 3);
+        assert_eq!(body["urls"], json!(["https://a.com", "https://b.com"]));
+        assert_eq!(body["ids"], json!(["id-1"]));
+        assert_eq!(body["text"], json!(true));
+    }
+
+    #[test]
+    fn contents_targets_drop_a_list_that_empties() {
+        let mut body = object(json!({ "urls": ["x"], "ids": ["x"] }));
+        assert_eq!(dedupe_contents_targets(&mut body), 1);
+        assert_eq!(body["urls"], json!(["x"]));
+        assert!(!body.contains_key("ids"));
+    }
+
+    #[test]
+    fn normalize_list_trims_skips_blanks_and_repeats() {
+        let items = ["a", " b ", "", "a", "  "].map(String::from);
+        assert_eq!(normalize_list(&items), Some(vec![json!("a"), json!("b")]));
+        assert_eq!(normalize_list(&[" ".to_string()]), None);
+    }
+
+    #[test]
+    fn duplicate_results_are_counted_by_exact_url() {
+        let payload = json!({ "results": [
+            { "url": "https://a.com" },
+            { "url": "https://b.com" },
+            { "url": "https://a.com" },
+            { "id": "no-url" },
+        ]});
+        assert_eq!(count_duplicate_results(&payload), 1);
+        assert_eq!(count_duplicate_results(&json!({ "answer": "x" })), 0);
+    }
+
+    #[test]
+    fn canonical_url_normalises_host_slash_and_tracking() {
+        assert_eq!(
+            canonical_url("https://Example.COM/Path/?utm_source=x&id=7&fbclid=y"),
+            "https://example.com/Path?id=7"
+        );
+        assert_eq!(
+            canonical_url(" https://example.com:8443/a/ "),
+            "https://example.com:8443/a"
+        );
+        assert_eq!(canonical_url("https://example.com/"), "https://example.com");
+        assert_eq!(canonical_url("not a url "), "not a url");
+    }
+
+    #[test]
+    fn dedupe_results_keeps_the_best_score_in_the_first_slot() {
+        let mut results = vec![
+            json!({ "url": "https://a.com/x?utm_medium=mail", "score": 0.2, "n": 1 }),
+            json!({ "url": "https://b.com", "score": 0.5, "n": 2 }),
+            json!({ "url": "https://A.com/x/", "score": 0.9, "n": 3 }),
+            json!({ "url": "https://a.com/x", "score": 0.1, "n": 4 }),
+            json!({ "title": "no url", "n": 5 }),
+        ];
+        assert_eq!(dedupe_results(&mut results), 2);
+        let kept: Vec<&Value> = results.iter().map(|result| &result["n"]).collect();
+        assert_eq!(kept, [&json!(3), &json!(2), &json!(5)]);
+    }
+
+    #[test]
+    fn dedupe_results_keeps_the_first_copy_without_scores() {
+        let mut results = vec![
+            json!({ "url": "https://a.com", "n": 1 }),
+            json!({ "url": "https://a.com/", "n": 2 }),
+        ];
+        assert_eq!(dedupe_results(&mut results), 1);
+        assert_eq!(results, [json!({ "url": "https://a.com", "n": 1 })]);
+    }
+
+    fn search_args(argv: &[&str]) -> Result<SearchArgs, clap::Error> {
+        let cli = Cli::try_parse_from(["exa", "search"].iter().chain(argv))?;
+        match cli.command {
+            Some(Command::Search(args)) => Ok(args),
+            _ => panic!("parsed another subcommand"),
+        }
+    }
+
+    #[test]
+    fn output_format_parses_for_queries_files_only() {
+        let args = search_args(&["--queries-file", "q.txt"]).expect("valid");
+        assert!(args.output_format.is_none());
+        let args =
+            search_args(&["--queries-file", "q.txt", "--output-format", "jsonl"]).expect("valid");
+        assert!(args.output_format == Some(LinesFormat::Jsonl));
+        let args = search_args(&[
+            "--queries-file",
+            "q.txt",
+            "--output-format",
+            "jsonl-wrapped",
+        ])
+        .expect("valid");
+        assert!(args.output_format == Some(LinesFormat::JsonlWrapped));
+        assert!(search_args(&["--query", "x", "--output-format", "jsonl"]).is_err());
+        assert!(search_args(&["--queries-file", "q.txt", "--output-format", "csv"]).is_err());
+    }
+
+    /// Parses `argv` and resolves its stdin inputs from `stdin`, then builds
+    /// the search body as `run_once` does for `--query`.
+    fn stdin_search_body(argv: &[&str], stdin: &str) -> Result<Map<String, Value>> {
+        let mut cli = Cli::try_parse_from(["exa"].iter().chain(argv))?;
+        let mut command = cli.command.take().expect("a subcommand");
+        read_stdin_inputs(&mut command, &mut stdin.as_bytes())?;
+        let Command::Search(args) = command else {
+            panic!("parsed another subcommand");
+        };
+        let mut body = load_body(&args.body)?;
+        if let Some(query) = args.query {
+            merge_flag(&mut body, "query", Value::String(query), cli.body_merge)?;
+        }
+        Ok(body)
+    }
+
+    const STDIN_BODY: &str = r#"{"query": "from stdin", "numResults": 2}"#;
+
+    #[test]
+    fn body_stdin_merges_with_flags() {
+        let body = stdin_search_body(&["search", "--body-stdin", "--query", "flag"], STDIN_BODY)
+            .expect("valid");
+        assert_eq!(
+            Value::Object(body),
+            json!({ "query": "flag", "numResults": 2 })
+        );
+    }
+
+    #[test]
+    fn body_stdin_follows_body_merge() {
+        let argv = [
+            "--body-merge",
+            "body-wins",
+            "search",
+            "--body-stdin",
+            "--query",
+            "flag",
+        ];
+        let body = stdin_search_body(&argv, STDIN_BODY).expect("valid");
+        assert_eq!(body["query"], "from stdin");
+
+        let argv = [
+            "--body-merge",
+            "error-on-conflict",
+            "search",
+            "--body-stdin",
+            "--query",
+            "flag",
+        ];
+        let err = stdin_search_body(&argv, STDIN_BODY).expect_err("conflict");
+        assert!(err.to_string().contains("query is set by both"), "{err}");
+    }
+
+    #[test]
+    fn body_stdin_alone_is_the_body() {
+        let body = stdin_search_body(&["search", "--body-stdin"], STDIN_BODY).expect("valid");
+        assert_eq!(body["query"], "from stdin");
+        let argv = ["search", "--body-stdin", "--body-format", "json5"];
+        let body = stdin_search_body(&argv, "{query: 'five', // note\n}").expect("json5");
+        assert_eq!(Value::Object(body), json!({ "query": "five" }));
+    }
+
+    #[test]
+    fn query_dash_reads_a_trimmed_query() {
+        let body = stdin_search_body(&["search", "--query", "-"], "robots\n\n").expect("valid");
+        assert_eq!(body["query"], "robots");
+    }
+
+    #[test]
+    fn stdin_inputs_reject_bad_combinations() {
+        let err = stdin_search_body(&["search", "--body-stdin", "--query", "-"], STDIN_BODY)
+            .expect_err("both read stdin");
+        assert!(err.to_string().contains("cannot both read stdin"), "{err}");
+        let err = stdin_search_body(&["search", "--body-stdin"], " \n").expect_err("empty");
+        assert!(err.to_string().contains("empty body"), "{err}");
+        let err = stdin_search_body(&["search", "--body-stdin"], "[1]").expect_err("array");
+        assert!(err.to_string().contains("JSON object"), "{err}");
+        assert!(stdin_search_body(&["search", "--body-stdin", "--body", "{}"], "{}").is_err());
+    }
+
+    /// Runs `argv` once and returns the body its history entry would store.
+    async fn sent_body(base: &str, argv: &[String]) -> Option<Value> {
+        let mut cli = Cli::try_parse_from(["exa".to_string()].iter().chain(argv)).expect("valid");
+        let command = cli.command.take().expect("a subcommand");
+        let api = test_api(base, 0);
+        let mut sent = None;
+        run_once(&cli, command, &api, &mut None, &mut sent)
+            .await
+            .expect("ran");
+        sent
+    }
+
+    #[tokio::test]
+    async fn rerun_of_a_diff_keeps_its_own_flags() {
+        let dir = std::env::temp_dir().join(format!("exa-rerun-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).expect("temp dir");
+        let (old, new) = (dir.join("old.json"), dir.join("new.json"));
+        fs::write(&old, r#"{"results": [{"url": "https://a.com"}]}"#).expect("write old");
+        fs::write(&new, r#"{"results": [{"url": "https://b.com"}]}"#).expect("write new");
+        let argv: Vec<String> = ["diff", "--summary"]
+            .into_iter()
+            .map(str::to_string)
+            .chain([old, new].map(|path| path.display().to_string()))
+            .collect();
+        let sent = sent_body("http://127.0.0.1:9", &argv).await;
+        assert!(sent.is_none());
+        let replayed = history::replay_args(argv.clone(), sent.as_ref());
+        assert_eq!(replayed, argv);
+        assert!(Cli::try_parse_from(["exa".to_string()].iter().chain(&replayed)).is_ok());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn contents_from_search_records_the_search_body() {
+        let (base, requests) = testserver::serve_recorded(vec![
+            (
+                Duration::ZERO,
+                200,
+                r#"{"results": [{"id": "a", "url": "https://a.com"}]}"#,
+            ),
+            (
+                Duration::ZERO,
+                200,
+                r#"{"results": [{"id": "a", "url": "https://a.com", "text": "A"}]}"#,
+            ),
+        ]);
+        let argv: Vec<String> = ["search", "--query", "robots", "--contents-from-search"]
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let sent = sent_body(&base, &argv).await.expect("a body");
+        let requests = requests.lock().expect("requests").clone();
+        let paths: Vec<&str> = requests.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, ["/search", "/contents"]);
+        assert_eq!(
+            sent,
+            serde_json::from_str::<Value>(&requests[0].1).expect("json")
+        );
+        assert_eq!(sent_label(&sent), Some(json!("robots")));
+        let replayed = history::replay_args(argv, Some(&sent));
+        assert_eq!(replayed[..2], ["search", "--contents-from-search"]);
+        assert!(Cli::try_parse_from(["exa".to_string()].iter().chain(&replayed)).is_ok());
+    }
+
+    fn parsed_command(argv: &[&str]) -> Command {
+        let mut cli = Cli::try_parse_from(["exa"].iter().chain(argv)).expect("valid");
+        cli.command.take().expect("a subcommand")
+    }
+
+    #[test]
+    fn bodies_file_names_the_flags_it_would_ignore() {
+        let command = parsed_command(&[
+            "search",
+            "--bodies-file",
+            "b.ndjson",
+            "--num-results",
+            "3",
+            "--since",
+            "7d",
+            "--include-domains",
+            "a.com",
+        ]);
+        assert_eq!(
+            bodies_file_ignored(&command),
+            ["--num-results", "--since", "--include-domains"]
+        );
+        let command = parsed_command(&[
+            "find-similar",
+            "--bodies-file",
+            "b.ndjson",
+            "--category",
+            "news",
+        ]);
+        assert_eq!(bodies_file_ignored(&command), ["--category"]);
+        let command = parsed_command(&["answer", "--bodies-file", "b.ndjson"]);
+        assert!(bodies_file_ignored(&command).is_empty());
+    }
+
+    fn strict_error(argv: &[&str]) -> Option<String> {
+        let mut cli = Cli::try_parse_from(["exa", "--strict"].iter().chain(argv)).expect("valid");
+        let command = cli.command.take().expect("a subcommand");
+        check_strict(&cli, &command)
+            .err()
+            .map(|err| err.to_string())
+    }
+
+    #[test]
+    fn strict_refuses_command_flags_next_to_a_bodies_file() {
+        let err = strict_error(&["search", "--bodies-file", "b.ndjson", "--num-results", "3"]);
+        assert!(
+            err.is_some_and(|err| err.contains("--num-results has no effect with --bodies-file"))
+        );
+        let err = strict_error(&["contents", "--bodies-file", "b.ndjson", "--urls", "a"]);
+        assert!(err.is_some_and(|err| err.contains("--urls")));
+        assert_eq!(strict_error(&["search", "--bodies-file", "b.ndjson"]), None);
+    }
+
+    #[test]
+    fn queries_file_refuses_a_query() {
+        let parsed =
+            Cli::try_parse_from(["exa", "search", "--queries-file", "q.txt", "--query", "x"]);
+        assert!(parsed.is_err());
+    }
+
+    #[tokio::test]
+    async fn strict_refuses_a_body_query_next_to_a_queries_file() {
+        let argv = [
+            "exa",
+            "--strict",
+            "search",
+            "--queries-file",
+            "q.txt",
+            "--body",
+            r#"{"query":"x"}"#,
+        ];
+        let mut cli = Cli::try_parse_from(argv).expect("valid");
+        let command = cli.command.take().expect("a subcommand");
+        let api = test_api("http://127.0.0.1:9", 0);
+        let err = run_once(&cli, command, &api, &mut None, &mut None)
+            .await
+            .expect_err("refused before the file is read");
+        assert!(
+            err.to_string()
+                .contains("replaced by each --queries-file line"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn strict_refuses_result_flags_without_results() {
+        let err = strict_error(&["context", "--query", "x", "--dedupe"]);
+        assert!(err.is_some_and(|err| err.contains("--dedupe has no effect")));
+        assert_eq!(strict_error(&["search", "--query", "x", "--dedupe"]), None);
+    }
+}
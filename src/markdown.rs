@@ -0,0 +1,193 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+const SKIPPED_ELEMENTS: [&str; 5] = ["script", "style", "nav", "noscript", "svg"];
+const BOILERPLATE_LINES: [&str; 10] = [
+    "skip to content",
+    "skip to main content",
+    "menu",
+    "toggle navigation",
+    "share",
+    "accept cookies",
+    "accept all cookies",
+    "sign in",
+    "log in",
+    "subscribe",
+];
+const MAX_SLUG_LEN: usize = 100;
+
+/// One Markdown note for a contents result: title heading, source link, body.
+pub(crate) fn result_markdown(result: &Value) -> String {
+    let url = result.get("url").and_then(Value::as_str).unwrap_or("");
+    let title = result
+        .get("title")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|title| !title.is_empty())
+        .unwrap_or(url);
+    let text = result.get("text").and_then(Value::as_str).unwrap_or("");
+    let body = clean(&html_to_markdown(text));
+    let mut out = format!("# {title}\n\nSource: <{url}>\n");
+    if !body.is_empty() {
+        out.push('\n');
+        out.push_str(&body);
+        out.push('\n');
+    }
+    out
+}
+
+/// Writes one `.md` file per result under `dir` and returns the paths.
+pub(crate) fn write_notes(dir: &Path, results: &[Value]) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir).with_context(|| format!("create output dir {}", dir.display()))?;
+    let mut used = HashSet::new();
+    let mut paths = Vec::new();
+    for result in results {
+        let url = result.get("url").and_then(Value::as_str).unwrap_or("page");
+        let base = slug(url);
+        let mut name = base.clone();
+        let mut n = 2;
+        while !used.insert(name.clone()) {
+            name = format!("{base}-{n}");
+            n += 1;
+        }
+        let path = dir.join(format!("{name}.md"));
+        fs::write(&path, result_markdown(result))
+            .with_context(|| format!("write {}", path.display()))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+fn slug(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let mut slug = String::new();
+    for c in rest.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug: String = slug.trim_matches('-').chars().take(MAX_SLUG_LEN).collect();
+    match slug.trim_end_matches('-') {
+        "" => "page".to_string(),
+        slug => slug.to_string(),
+    }
+}
+
+/// Converts the common block and inline HTML tags to Markdown and drops the
+/// rest. Text without tags passes through unchanged.
+fn html_to_markdown(html: &str) -> String {
+    let mut out = String::new();
+    let mut links: Vec<Option<String>> = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&decode_entities(&rest[..start]));
+        let after = &rest[start + 1..];
+        let is_tag = after
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '/' || c == '!');
+        let Some(end) = after.find('>').filter(|_| is_tag) else {
+            out.push('<');
+            rest = after;
+            continue;
+        };
+        let tag = &after[..end];
+        rest = &after[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if !closing && SKIPPED_ELEMENTS.contains(&name.as_str()) {
+            let close = format!("</{name}");
+            rest = match rest.to_ascii_lowercase().find(&close) {
+                Some(idx) => rest[idx..].find('>').map_or("", |gt| &rest[idx + gt + 1..]),
+                None => "",
+            };
+            continue;
+        }
+        match name.as_str() {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                out.push_str("\n\n");
+                if !closing {
+                    let level = name[1..].parse().unwrap_or(1);
+                    out.push_str(&"#".repeat(level));
+                    out.push(' ');
+                }
+            }
+            "p" | "div" | "section" | "article" | "header" | "footer" | "main" | "ul" | "ol"
+            | "table" | "tr" | "blockquote" | "pre" => out.push_str("\n\n"),
+            "br" => out.push('\n'),
+            "li" if !closing => out.push_str("\n- "),
+            "strong" | "b" => out.push_str("**"),
+            "em" | "i" => out.push('_'),
+            "code" => out.push('`'),
+            "a" if !closing => {
+                let href = attribute(tag, "href");
+                if href.is_some() {
+                    out.push('[');
+                }
+                links.push(href);
+            }
+            "a" => {
+                if let Some(Some(href)) = links.pop() {
+                    out.push_str(&format!("]({href})"));
+                }
+            }
+            _ => {}
+        }
+    }
+    out.push_str(&decode_entities(rest));
+    out
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let idx = lower.find(&format!("{name}="))?;
+    let value = &tag[idx + name.len() + 1..];
+    let value = match value.chars().next()? {
+        quote @ ('"' | '\'') => value[1..].split(quote).next()?,
+        _ => value.split(|c: char| c.is_whitespace()).next()?,
+    };
+    Some(decode_entities(value)).filter(|value| !value.is_empty())
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Trims trailing space, drops navigation boilerplate and collapses runs of
+/// blank lines.
+fn clean(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim_end) {
+        let lower = line.trim().to_ascii_lowercase();
+        if BOILERPLATE_LINES.contains(&lower.as_str()) {
+            continue;
+        }
+        if line.trim().is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(if line.trim().is_empty() { "" } else { line });
+    }
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,189 @@
+use std::{collections::BTreeMap, fmt::Write as _, fs, path::Path, sync::Mutex, time::Duration};
+
+use anyhow::{Context, Result};
+
+/// Upper bounds (seconds) of the request latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 9] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Counters for `--metrics-file`, shared by every request of one run.
+#[derive(Default)]
+pub(crate) struct Metrics {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    requests: BTreeMap<String, u64>,
+    retries: u64,
+    failures: BTreeMap<String, u64>,
+    results: u64,
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    latency_sum: f64,
+    latency_count: u64,
+}
+
+impl Metrics {
+    pub(crate) fn request(&self, path: &str, latency: Duration) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        *inner.requests.entry(endpoint(path)).or_default() += 1;
+        let secs = latency.as_secs_f64();
+        for (bucket, bound) in inner.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if secs <= bound {
+                *bucket += 1;
+            }
+        }
+        inner.latency_sum += secs;
+        inner.latency_count += 1;
+    }
+
+    pub(crate) fn retry(&self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.retries += 1;
+        }
+    }
+
+    /// `status` is the HTTP status code, or `network` when no response came.
+    pub(crate) fn failure(&self, status: &str) {
+        if let Ok(mut inner) = self.inner.lock() {
+            *inner.failures.entry(status.to_string()).or_default() += 1;
+        }
+    }
+
+    pub(crate) fn results(&self, count: usize) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.results += count as u64;
+        }
+    }
+
+    /// Writes the Prometheus text format to a temp file beside `path` and
+    /// renames it into place, so a scraper never sees a partial file.
+    pub(crate) fn write(&self, path: &Path) -> Result<()> {
+        let text = self.render();
+        let tmp = path.with_file_name(format!(
+            ".{}.tmp",
+            path.file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default()
+        ));
+        fs::write(&tmp, text).with_context(|| format!("write {}", tmp.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("rename to {}", path.display()))
+    }
+
+    fn render(&self) -> String {
+        let Ok(inner) = self.inner.lock() else {
+            return String::new();
+        };
+        let mut out = String::new();
+        let _ = writeln!(out, "# HELP exa_requests_total Requests sent, by endpoint.");
+        let _ = writeln!(out, "# TYPE exa_requests_total counter");
+        for (endpoint, count) in &inner.requests {
+            let _ = writeln!(out, "exa_requests_total{{endpoint=\"{endpoint}\"}} {count}");
+        }
+        let _ = writeln!(out, "# HELP exa_retries_total Requests retried.");
+        let _ = writeln!(out, "# TYPE exa_retries_total counter");
+        let _ = writeln!(out, "exa_retries_total {}", inner.retries);
+        let _ = writeln!(
+            out,
+            "# HELP exa_request_failures_total Failed requests, by HTTP status or network."
+        );
+        let _ = writeln!(out, "# TYPE exa_request_failures_total counter");
+        for (status, count) in &inner.failures {
+            let _ = writeln!(
+                out,
+                "exa_request_failures_total{{status=\"{status}\"}} {count}"
+            );
+        }
+        let _ = writeln!(out, "# HELP exa_results_total Results returned.");
+        let _ = writeln!(out, "# TYPE exa_results_total counter");
+        let _ = writeln!(out, "exa_results_total {}", inner.results);
+        let _ = writeln!(
+            out,
+            "# HELP exa_request_duration_seconds Request latency until response headers."
+        );
+        let _ = writeln!(out, "# TYPE exa_request_duration_seconds histogram");
+        for (count, bound) in inner.buckets.iter().zip(LATENCY_BUCKETS) {
+            let _ = writeln!(
+                out,
+                "exa_request_duration_seconds_bucket{{le=\"{bound}\"}} {count}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "exa_request_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+            inner.latency_count
+        );
+        let _ = writeln!(
+            out,
+            "exa_request_duration_seconds_sum {}",
+            inner.latency_sum
+        );
+        let _ = writeln!(
+            out,
+            "exa_request_duration_seconds_count {}",
+            inner.latency_count
+        );
+        out
+    }
+}
+
+/// Endpoint label for a request path, with research task ids folded so the
+/// label set stays small.
+fn endpoint(path: &str) -> String {
+    match path.strip_prefix("/research/v0/tasks/") {
+        Some(_) => "/research/v0/tasks/{id}".to_string(),
+        None => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_counts_requests_failures_and_latency() {
+        let metrics = Metrics::default();
+        metrics.request("/search", Duration::from_millis(30));
+        metrics.request("/research/v0/tasks/abc", Duration::from_secs(2));
+        metrics.retry();
+        metrics.failure("503");
+        metrics.results(7);
+        let text = metrics.render();
+        for line in [
+            "exa_requests_total{endpoint=\"/research/v0/tasks/{id}\"} 1",
+            "exa_requests_total{endpoint=\"/search\"} 1",
+            "exa_retries_total 1",
+            "exa_request_failures_total{status=\"503\"} 1",
+            "exa_results_total 7",
+            "exa_request_duration_seconds_bucket{le=\"0.05\"} 1",
+            "exa_request_duration_seconds_bucket{le=\"1\"} 1",
+            "exa_request_duration_seconds_bucket{le=\"2.5\"} 2",
+            "exa_request_duration_seconds_bucket{le=\"+Inf\"} 2",
+            "exa_request_duration_seconds_count 2",
+        ] {
+            assert!(
+                text.lines().any(|got| got == line),
+                "missing {line:?} in\n{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn write_replaces_the_file_without_leaving_a_temp_file() {
+        let dir = std::env::temp_dir().join(format!("exa-metrics-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("exa.prom");
+        fs::write(&path, "stale").unwrap();
+        let metrics = Metrics::default();
+        metrics.results(2);
+        metrics.write(&path).unwrap();
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .contains("exa_results_total 2")
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
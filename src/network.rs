@@ -0,0 +1,104 @@
+use std::{error::Error, fmt, time::Duration};
+
+/// Exit codes for requests that never got a response.
+pub(crate) const EXIT_DNS: i32 = 5;
+pub(crate) const EXIT_CONNECT: i32 = 6;
+pub(crate) const EXIT_READ_TIMEOUT: i32 = 7;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Failure {
+    Dns,
+    ConnectTimeout,
+    Connect,
+    ReadTimeout,
+}
+
+/// A transport failure classified for the error message and exit code.
+#[derive(Debug)]
+pub(crate) struct NetworkError {
+    pub(crate) failure: Failure,
+    timeout: Duration,
+    source: reqwest::Error,
+}
+
+impl NetworkError {
+    pub(crate) fn exit_code(&self) -> i32 {
+        match self.failure {
+            Failure::Dns => EXIT_DNS,
+            Failure::ConnectTimeout | Failure::Connect => EXIT_CONNECT,
+            Failure::ReadTimeout => EXIT_READ_TIMEOUT,
+        }
+    }
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.timeout.as_secs_f64();
+        let url = self.source.url().map(|url| url.as_str()).unwrap_or("");
+        match self.failure {
+            Failure::Dns => write!(f, "DNS failure for {url}: {}", root_cause(&self.source)),
+            Failure::ConnectTimeout => write!(f, "connect timeout after {secs}s ({url})"),
+            Failure::Connect => write!(f, "connect failed to {url}: {}", root_cause(&self.source)),
+            Failure::ReadTimeout => write!(f, "read timeout after {secs}s ({url})"),
+        }
+    }
+}
+
+impl Error for NetworkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Wraps timeouts and connection failures in a `NetworkError`; other errors
+/// get `context`. `timeout` is the limit the request ran under.
+pub(crate) fn classify(
+    err: reqwest::Error,
+    timeout: Duration,
+    context: &'static str,
+) -> anyhow::Error {
+    let failure = if is_dns(&err) {
+        Failure::Dns
+    } else if err.is_connect() && err.is_timeout() {
+        Failure::ConnectTimeout
+    } else if err.is_connect() {
+        Failure::Connect
+    } else if err.is_timeout() {
+        Failure::ReadTimeout
+    } else {
+        return anyhow::Error::new(err).context(context);
+    };
+    NetworkError {
+        failure,
+        timeout,
+        source: err,
+    }
+    .into()
+}
+
+/// Exit code for an error returned from `run`.
+pub(crate) fn exit_code(err: &anyhow::Error) -> i32 {
+    err.downcast_ref::<NetworkError>()
+        .map_or(1, NetworkError::exit_code)
+}
+
+// hyper-util reports resolver failures as "dns error: ..." somewhere in the
+// source chain of a connect error.
+fn is_dns(err: &reqwest::Error) -> bool {
+    let mut source = err.source();
+    while let Some(err) = source {
+        if err.to_string().starts_with("dns error") {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+fn root_cause(err: &(dyn Error + 'static)) -> String {
+    let mut cause = err;
+    while let Some(next) = cause.source() {
+        cause = next;
+    }
+    cause.to_string()
+}
@@ -0,0 +1,464 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+use reqwest::Url;
+use serde_json::{Map, Value};
+
+use crate::result_list;
+
+/// Results listed by `--summary` before the rest are counted.
+const SUMMARY_TOP: usize = 5;
+
+pub(crate) fn answer_markdown(payload: &Value) -> Option<String> {
+    let answer = payload.get("answer")?.as_str()?;
+    let citations = payload.get("citations")?.as_array()?;
+    if citations.is_empty() {
+        return None;
+    }
+    let mut out = answer.trim_end().to_string();
+    out.push_str("\n\n## Sources\n");
+    for (idx, citation) in citations.iter().enumerate() {
+        let url = citation.get("url").and_then(Value::as_str).unwrap_or("");
+        let title = citation
+            .get("title")
+            .and_then(Value::as_str)
+            .filter(|title| !title.trim().is_empty())
+            .unwrap_or(url);
+        out.push_str(&format!(
+            "\n{}. [{}]({url})",
+            idx + 1,
+            escape_link_text(title.trim())
+        ));
+    }
+    Some(out)
+}
+
+pub(crate) fn results_markdown(results: &[Value]) -> String {
+    let mut lines = Vec::new();
+    for (idx, result) in results.iter().enumerate() {
+        let url = result.get("url").and_then(Value::as_str).unwrap_or("");
+        let title = result
+            .get("title")
+            .and_then(Value::as_str)
+            .filter(|title| !title.trim().is_empty())
+            .unwrap_or(url);
+        lines.push(format!(
+            "{}. [{}]({url})",
+            idx + 1,
+            escape_link_text(title.trim())
+        ));
+        let note = ["summary", "snippet"]
+            .iter()
+            .find_map(|key| result.get(*key).and_then(Value::as_str))
+            .map(str::trim)
+            .filter(|note| !note.is_empty());
+        if let Some(note) = note {
+            lines.push(format!("   {}", note.replace('\n', " ")));
+        }
+    }
+    lines.join("\n")
+}
+
+/// A short human summary of a response: result count and the top titles, or
+/// the answer's citation count.
+pub(crate) fn summary(payload: &Value) -> String {
+    if let Some(results) = result_list(payload) {
+        let mut lines = vec![format!("{} results", results.len())];
+        for result in results.iter().take(SUMMARY_TOP) {
+            let url = result.get("url").and_then(Value::as_str).unwrap_or("");
+            match result.get("title").and_then(Value::as_str).map(str::trim) {
+                Some(title) if !title.is_empty() => lines.push(format!("- {title}  {url}")),
+                _ => lines.push(format!("- {url}")),
+            }
+        }
+        if results.len() > SUMMARY_TOP {
+            lines.push(format!("  … {} more", results.len() - SUMMARY_TOP));
+        }
+        return lines.join("\n");
+    }
+    if let Some(answer) = payload.get("answer").and_then(Value::as_str) {
+        let citations = payload
+            .get("citations")
+            .and_then(Value::as_array)
+            .map_or(0, Vec::len);
+        let first = answer.lines().next().unwrap_or("").trim();
+        return format!("answer with {citations} citations\n{first}");
+    }
+    match payload.get("status").and_then(Value::as_str) {
+        Some(status) => format!("status: {status}"),
+        None => "response written".to_string(),
+    }
+}
+
+fn escape_link_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
+
+/// Result counts per domain, most frequent first, as an aligned table.
+pub(crate) fn domains_summary(results: &[Value]) -> String {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for result in results {
+        let Some(url) = result.get("url").and_then(Value::as_str) else {
+            continue;
+        };
+        let domain = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+            .unwrap_or_else(|| url.to_string());
+        let domain = domain.strip_prefix("www.").unwrap_or(&domain).to_string();
+        *counts.entry(domain).or_default() += 1;
+    }
+    let mut rows: Vec<(String, usize)> = counts.into_iter().collect();
+    rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let width = rows.first().map_or(1, |(_, count)| count.to_string().len());
+    rows.iter()
+        .map(|(domain, count)| format!("{count:>width$}  {domain}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub(crate) fn render_template(template: &str, results: &[Value]) -> String {
+    results
+        .iter()
+        .map(|result| fill_template(template, result))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn fill_template(template: &str, result: &Value) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        match tail.strip_prefix('{').and_then(|t| t.split_once('}')) {
+            Some((field, after)) => {
+                out.push_str(&field_text(resolve_path(result, field.trim())));
+                rest = after;
+            }
+            None => {
+                out.push_str(&tail[..1]);
+                rest = &tail[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub(crate) fn resolve_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    // A literal dotted key (from --flatten) wins over walking the path.
+    if let Some(found) = value.get(path) {
+        return Some(found);
+    }
+    let normalized = path.replace('[', ".").replace(']', "");
+    normalized
+        .split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |current, segment| match current {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+pub(crate) fn project_fields(results: &[Value], fields: &[String]) -> Vec<Value> {
+    results
+        .iter()
+        .map(|result| {
+            let row = fields
+                .iter()
+                .map(|field| {
+                    let value = resolve_path(result, field).cloned().unwrap_or(Value::Null);
+                    (field.clone(), value)
+                })
+                .collect();
+            Value::Object(row)
+        })
+        .collect()
+}
+
+pub(crate) fn render_csv(results: &[Value], fields: &[String]) -> String {
+    let columns: Vec<String> = if fields.is_empty() {
+        let mut keys: Vec<String> = results
+            .iter()
+            .filter_map(Value::as_object)
+            .flat_map(|map| {
+                map.iter()
+                    .filter(|(_, value)| !value.is_array() && !value.is_object())
+                    .map(|(key, _)| key.clone())
+            })
+            .collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    } else {
+        fields.to_vec()
+    };
+    let mut lines = vec![
+        columns
+            .iter()
+            .map(|c| csv_cell(c))
+            .collect::<Vec<_>>()
+            .join(","),
+    ];
+    for result in results {
+        let row: Vec<String> = columns
+            .iter()
+            .map(|column| csv_cell(&field_text(resolve_path(result, column))))
+            .collect();
+        lines.push(row.join(","));
+    }
+    lines.join("\n")
+}
+
+fn csv_cell(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn field_text(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(text)) => text.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Rewrites nested objects as dotted top-level keys (`extras.links`). Arrays
+/// are kept as values unless `arrays` is set, which indexes them too
+/// (`highlights.0`).
+pub(crate) fn flatten(value: &Value, arrays: bool) -> Value {
+    let mut out = Map::new();
+    flatten_into(&mut out, "", value, arrays);
+    Value::Object(out)
+}
+
+fn flatten_into(out: &mut Map<String, Value>, prefix: &str, value: &Value, arrays: bool) {
+    let join = |key: &str| match prefix {
+        "" => key.to_string(),
+        _ => format!("{prefix}.{key}"),
+    };
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, item) in map {
+                flatten_into(out, &join(key), item, arrays);
+            }
+        }
+        Value::Array(items) if arrays && !items.is_empty() => {
+            for (idx, item) in items.iter().enumerate() {
+                flatten_into(out, &join(&idx.to_string()), item, arrays);
+            }
+        }
+        _ => {
+            out.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
+
+/// Tidies the `text` and `summary` of each result: trailing whitespace is
+/// dropped from every line, runs of blank lines become one, and the whole
+/// string is trimmed. Indentation and single line breaks are kept.
+pub(crate) fn trim_whitespace(results: &mut [Value]) {
+    for result in results {
+        for field in ["text", "summary"] {
+            if let Some(Value::String(text)) = result.get_mut(field) {
+                *text = tidy(text);
+            }
+        }
+    }
+}
+
+fn tidy(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut blank = false;
+    for line in text.trim().lines().map(str::trim_end) {
+        if line.is_empty() {
+            blank = true;
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(if blank { "\n\n" } else { "\n" });
+        }
+        out.push_str(line);
+        blank = false;
+    }
+    out
+}
+
+pub(crate) fn truncate_strings(value: &mut Value, max: usize) {
+    match value {
+        Value::String(text) => {
+            let total = text.chars().count();
+            if total > max {
+                let kept: String = text.chars().take(max).collect();
+                *text = format!("{kept}… (+{} chars)", total - max);
+            }
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| truncate_strings(item, max)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|item| truncate_strings(item, max)),
+        _ => {}
+    }
+}
+
+pub(crate) fn render_export(payload: &Value, prefix: &str) -> Result<String> {
+    if !prefix
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        || prefix.starts_with(|c: char| c.is_ascii_digit())
+    {
+        return Err(anyhow!(
+            "--export-prefix {prefix:?} is not a valid variable name prefix"
+        ));
+    }
+    let Value::Object(map) = payload else {
+        return Err(anyhow!("--export needs a JSON object response"));
+    };
+    let lines: Vec<String> = map
+        .iter()
+        .filter_map(|(key, value)| {
+            let text = match value {
+                Value::String(text) => text.clone(),
+                Value::Number(_) | Value::Bool(_) => value.to_string(),
+                _ => return None,
+            };
+            Some(format!("{prefix}{}={}", env_name(key), shell_quote(&text)))
+        })
+        .collect();
+    if lines.is_empty() {
+        return Err(anyhow!("--export found no scalar fields in the response"));
+    }
+    Ok(lines.join("\n"))
+}
+
+fn env_name(key: &str) -> String {
+    let mut out = String::new();
+    let mut prev_lower = false;
+    for c in key.chars() {
+        if c.is_ascii_uppercase() && prev_lower {
+            out.push('_');
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        out.push(if c.is_ascii_alphanumeric() {
+            c.to_ascii_uppercase()
+        } else {
+            '_'
+        });
+    }
+    out
+}
+
+/// Quotes `text` only when the shell would otherwise split or expand it.
+pub(crate) fn shell_word(text: &str) -> String {
+    let plain = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=,@%+".contains(c));
+    if plain {
+        text.to_string()
+    } else {
+        shell_quote(text)
+    }
+}
+
+pub(crate) fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn templates_fill_fields_and_escape_braces() {
+        let result = json!({ "title": "T", "extras": { "links": ["a", "b"] }, "score": 0.5 });
+        assert_eq!(
+            fill_template("{{title}} is {title}", &result),
+            "{title} is T"
+        );
+        assert_eq!(fill_template("{extras.links[1]} {score}", &result), "b 0.5");
+        assert_eq!(fill_template("[{ author }]", &result), "[]");
+        assert_eq!(fill_template("{open and } close}", &result), " close}");
+        assert_eq!(fill_template("left { only", &result), "left { only");
+    }
+
+    #[test]
+    fn csv_quotes_only_cells_that_need_it() {
+        let results = [
+            json!({ "title": "a,b", "url": "u", "extras": {} }),
+            json!({ "title": "say \"hi\"", "url": "v\nw" }),
+        ];
+        assert_eq!(
+            render_csv(&results, &[]),
+            "title,url\n\"a,b\",u\n\"say \"\"hi\"\"\",\"v\nw\""
+        );
+        let fields = ["url".to_string(), "author".to_string()];
+        assert_eq!(render_csv(&results[..1], &fields), "url,author\nu,");
+    }
+
+    #[test]
+    fn flatten_joins_keys_and_optionally_indexes_arrays() {
+        let value = json!({ "a": { "b": 1, "c": [1, 2] }, "d": {} });
+        assert_eq!(
+            flatten(&value, false),
+            json!({ "a.b": 1, "a.c": [1, 2], "d": {} })
+        );
+        assert_eq!(
+            flatten(&value, true),
+            json!({ "a.b": 1, "a.c.0": 1, "a.c.1": 2, "d": {} })
+        );
+    }
+
+    #[test]
+    fn tidy_trims_lines_and_collapses_blank_runs() {
+        assert_eq!(
+            tidy("  line one  \n\n\n  indented\nnext \n"),
+            "line one\n\n  indented\nnext"
+        );
+        let mut results = [json!({ "text": " a \n\n\nb ", "title": " kept " })];
+        trim_whitespace(&mut results);
+        assert_eq!(results[0], json!({ "text": "a\n\nb", "title": " kept " }));
+    }
+
+    #[test]
+    fn export_prints_scalar_fields_as_quoted_variables() {
+        let payload = json!({
+            "requestId": "r1",
+            "autopromptString": "it's",
+            "count": 3,
+            "costDollars": { "total": 1 },
+        });
+        assert_eq!(
+            render_export(&payload, "EXA_").unwrap(),
+            "EXA_AUTOPROMPT_STRING='it'\\''s'\nEXA_COUNT='3'\nEXA_REQUEST_ID='r1'"
+        );
+        assert!(render_export(&payload, "1X").is_err());
+        assert!(render_export(&payload, "A-B").is_err());
+        assert!(render_export(&json!([1]), "EXA_").is_err());
+        assert!(render_export(&json!({ "list": [] }), "EXA_").is_err());
+    }
+
+    #[test]
+    fn env_names_split_camel_case() {
+        assert_eq!(env_name("userLocation"), "USER_LOCATION");
+        assert_eq!(env_name("num_results2Beta"), "NUM_RESULTS2_BETA");
+        assert_eq!(env_name("a.b"), "A_B");
+        assert_eq!(env_name("URLList"), "URLLIST");
+    }
+}
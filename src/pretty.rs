@@ -0,0 +1,134 @@
+use std::{cell::Cell, io};
+
+use anyhow::Result;
+use serde::{
+    Serialize, Serializer,
+    ser::{SerializeMap, SerializeSeq},
+};
+use serde_json::{
+    Value,
+    ser::{Formatter, PrettyFormatter},
+};
+
+/// Indented JSON with arrays of scalars kept on one line (`--compact-arrays`).
+pub(crate) fn to_string(value: &Value) -> Result<String> {
+    let compact = Cell::new(false);
+    let formatter = CompactArrays {
+        pretty: PrettyFormatter::new(),
+        compact: &compact,
+    };
+    let mut out = Vec::new();
+    let mut serializer = serde_json::Serializer::with_formatter(&mut out, formatter);
+    Tagged {
+        value,
+        compact: &compact,
+    }
+    .serialize(&mut serializer)?;
+    Ok(String::from_utf8(out)?)
+}
+
+/// Walks the value and raises `compact` around every array that holds only
+/// scalars, so the formatter knows to keep it on one line.
+struct Tagged<'a> {
+    value: &'a Value,
+    compact: &'a Cell<bool>,
+}
+
+impl Serialize for Tagged<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let child = |value| Tagged {
+            value,
+            compact: self.compact,
+        };
+        match self.value {
+            Value::Array(items) if items.iter().all(is_scalar) => {
+                self.compact.set(true);
+                let result = self.value.serialize(serializer);
+                self.compact.set(false);
+                result
+            }
+            Value::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(&child(item))?;
+                }
+                seq.end()
+            }
+            Value::Object(map) => {
+                let mut out = serializer.serialize_map(Some(map.len()))?;
+                for (key, value) in map {
+                    out.serialize_entry(key, &child(value))?;
+                }
+                out.end()
+            }
+            scalar => scalar.serialize(serializer),
+        }
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Array(_) | Value::Object(_))
+}
+
+struct CompactArrays<'a> {
+    pretty: PrettyFormatter<'static>,
+    compact: &'a Cell<bool>,
+}
+
+impl Formatter for CompactArrays<'_> {
+    fn begin_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        if self.compact.get() {
+            return writer.write_all(b"[");
+        }
+        self.pretty.begin_array(writer)
+    }
+
+    fn end_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        if self.compact.get() {
+            return writer.write_all(b"]");
+        }
+        self.pretty.end_array(writer)
+    }
+
+    fn begin_array_value<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        if self.compact.get() {
+            return writer.write_all(if first { b"" } else { b", " });
+        }
+        self.pretty.begin_array_value(writer, first)
+    }
+
+    fn end_array_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        if self.compact.get() {
+            return Ok(());
+        }
+        self.pretty.end_array_value(writer)
+    }
+
+    fn begin_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.begin_object(writer)
+    }
+
+    fn end_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.end_object(writer)
+    }
+
+    fn begin_object_key<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.pretty.begin_object_key(writer, first)
+    }
+
+    fn begin_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.begin_object_value(writer)
+    }
+
+    fn end_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.end_object_value(writer)
+    }
+}
@@ -0,0 +1,76 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Map, Value};
+
+use crate::config;
+
+pub(crate) fn save(name: &str, body: &Map<String, Value>) -> Result<PathBuf> {
+    let path = query_path(name)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+    }
+    let raw = serde_json::to_string_pretty(body)?;
+    fs::write(&path, format!("{raw}\n"))
+        .with_context(|| format!("write saved query {}", path.display()))?;
+    Ok(path)
+}
+
+pub(crate) fn load(name: &str) -> Result<Map<String, Value>> {
+    let path = query_path(name)?;
+    if !path.exists() {
+        return Err(anyhow!(
+            "no saved query named {name:?} (see `exa queries list`)"
+        ));
+    }
+    let raw = fs::read_to_string(&path)
+        .with_context(|| format!("read saved query {}", path.display()))?;
+    match serde_json::from_str(&raw)
+        .with_context(|| format!("parse saved query {}", path.display()))?
+    {
+        Value::Object(map) => Ok(map),
+        _ => Err(anyhow!(
+            "saved query {} is not a JSON object",
+            path.display()
+        )),
+    }
+}
+
+pub(crate) fn list() -> Result<Vec<String>> {
+    let dir = queries_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut names: Vec<String> = fs::read_dir(&dir)
+        .with_context(|| format!("read {}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            path.file_stem()?.to_str().map(str::to_string)
+        })
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+fn queries_dir() -> Result<PathBuf> {
+    config::config_dir()
+        .map(|dir| dir.join("queries"))
+        .context("cannot locate config dir (set HOME or XDG_CONFIG_HOME)")
+}
+
+fn query_path(name: &str) -> Result<PathBuf> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(anyhow!(
+            "invalid query name {name:?}: use letters, digits, '-' or '_'"
+        ));
+    }
+    Ok(queries_dir()?.join(format!("{name}.json")))
+}
@@ -0,0 +1,183 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+#[derive(Serialize, Deserialize)]
+struct Recording {
+    method: String,
+    path: String,
+    body: Option<Value>,
+    status: u16,
+    response: String,
+}
+
+pub(crate) fn save(
+    dir: &Path,
+    method: &str,
+    path: &str,
+    body: Option<&[u8]>,
+    status: StatusCode,
+    response: &str,
+) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("create record dir {}", dir.display()))?;
+    let recording = Recording {
+        method: method.to_string(),
+        path: path.to_string(),
+        body: body.and_then(|b| serde_json::from_slice(b).ok()),
+        status: status.as_u16(),
+        response: response.to_string(),
+    };
+    let file = dir.join(file_name(method, path, body));
+    let raw = serde_json::to_string_pretty(&recording)?;
+    fs::write(&file, raw).with_context(|| format!("write recording {}", file.display()))
+}
+
+pub(crate) fn load(
+    dir: &Path,
+    method: &str,
+    path: &str,
+    body: Option<&[u8]>,
+) -> Result<(StatusCode, String)> {
+    let file = dir.join(file_name(method, path, body));
+    if !file.exists() {
+        return Err(anyhow!(
+            "no recording for {method} {path} (body hash {}) in {}",
+            body_hash(method, path, body),
+            dir.display()
+        ));
+    }
+    let raw =
+        fs::read_to_string(&file).with_context(|| format!("read recording {}", file.display()))?;
+    let recording: Recording = serde_json::from_str(&raw)
+        .with_context(|| format!("parse recording {}", file.display()))?;
+    let status = StatusCode::from_u16(recording.status)
+        .with_context(|| format!("invalid status in recording {}", file.display()))?;
+    Ok((status, recording.response))
+}
+
+fn file_name(method: &str, path: &str, body: Option<&[u8]>) -> String {
+    let endpoint: String = path
+        .trim_matches('/')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!(
+        "{}-{endpoint}-{}.json",
+        method.to_ascii_lowercase(),
+        body_hash(method, path, body)
+    )
+}
+
+/// `value` with object keys sorted at every level; array order is kept.
+/// Two values that differ only in key order serialize to the same bytes.
+pub(crate) fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), canonicalize(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Hashes the method, path and body. A JSON body is canonicalized first, so
+/// bodies that differ only in key order or whitespace share a recording.
+fn body_hash(method: &str, path: &str, body: Option<&[u8]>) -> String {
+    let body = body.unwrap_or_default();
+    let normalized = serde_json::from_slice::<Value>(body)
+        .ok()
+        .map(|value| canonicalize(&value).to_string());
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_bytes());
+    hasher.update(b" ");
+    hasher.update(path.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(normalized.as_deref().map_or(body, str::as_bytes));
+    let digest = hasher.finalize();
+    digest[..8].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn key_order_and_whitespace_do_not_change_the_hash() {
+        let a = br#"{"query":"robots","contents":{"text":true,"summary":{"query":"q"}},"numResults":3}"#;
+        let b = br#"{ "numResults": 3,
+            "contents": { "summary": { "query": "q" }, "text": true },
+            "query": "robots" }"#;
+        assert_eq!(
+            body_hash("POST", "/search", Some(a)),
+            body_hash("POST", "/search", Some(b))
+        );
+        assert_eq!(
+            file_name("POST", "/search", Some(a)),
+            file_name("POST", "/search", Some(b))
+        );
+    }
+
+    #[test]
+    fn reordered_body_serializes_to_identical_bytes() {
+        let a: Value =
+            serde_json::from_str(r#"{"b":2,"a":{"y":[1,{"d":4,"c":3}],"x":1}}"#).unwrap();
+        let b: Value =
+            serde_json::from_str(r#"{"a":{"x":1,"y":[1,{"c":3,"d":4}]},"b":2}"#).unwrap();
+        assert_eq!(canonicalize(&a).to_string(), canonicalize(&b).to_string());
+        assert_eq!(
+            canonicalize(&a).to_string(),
+            r#"{"a":{"x":1,"y":[1,{"c":3,"d":4}]},"b":2}"#
+        );
+    }
+
+    #[test]
+    fn canonicalize_keeps_array_order_and_scalars() {
+        let value = json!({ "urls": ["b", "a"], "n": 1.5, "flag": null });
+        assert_eq!(canonicalize(&value), value);
+        assert_eq!(canonicalize(&json!("text")), json!("text"));
+    }
+
+    #[test]
+    fn different_bodies_or_endpoints_hash_differently() {
+        let body = Some(&br#"{"query":"robots"}"#[..]);
+        let other = Some(&br#"{"query":"drones"}"#[..]);
+        assert_ne!(
+            body_hash("POST", "/search", body),
+            body_hash("POST", "/search", other)
+        );
+        assert_ne!(
+            body_hash("POST", "/search", body),
+            body_hash("POST", "/answer", body)
+        );
+    }
+
+    #[test]
+    fn array_order_still_matters() {
+        let a = Some(&br#"{"urls":["a","b"]}"#[..]);
+        let b = Some(&br#"{"urls":["b","a"]}"#[..]);
+        assert_ne!(
+            body_hash("POST", "/contents", a),
+            body_hash("POST", "/contents", b)
+        );
+    }
+
+    #[test]
+    fn non_json_bodies_hash_as_raw_bytes() {
+        let hash = body_hash("POST", "/search", Some(b"not json"));
+        assert_eq!(hash.len(), 16);
+        assert_ne!(hash, body_hash("POST", "/search", Some(b"not  json")));
+    }
+}
@@ -0,0 +1,145 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+
+use crate::{Api, RequestOptions, exa_get_with, retry};
+
+const TERMINAL_STATUSES: [&str; 3] = ["completed", "failed", "canceled"];
+
+pub(crate) struct Wait {
+    pub(crate) timeout: Duration,
+    pub(crate) interval: Duration,
+    pub(crate) poll_timeout: Option<Duration>,
+}
+
+/// Polls a research task until it reaches a terminal status or `wait.timeout`
+/// runs out. A poll that times out or hits a transient error is reported and
+/// the loop carries on; it only costs the time it took. Polls bypass the
+/// circuit breaker, so a run of slow polls cannot cut the wait short.
+pub(crate) async fn wait(api: &Api, task_id: &str, wait: &Wait) -> Result<Value> {
+    let path = format!("/research/v0/tasks/{task_id}");
+    let opts = RequestOptions {
+        timeout: wait.poll_timeout,
+        skip_breaker: true,
+        ..RequestOptions::default()
+    };
+    let started = Instant::now();
+    let mut status = "pending".to_string();
+    loop {
+        match exa_get_with(api, &path, &opts).await {
+            Ok(task) => {
+                status = task
+                    .get("status")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown")
+                    .to_string();
+                if TERMINAL_STATUSES.contains(&status.as_str()) {
+                    return Ok(task);
+                }
+                if api.verbose >= 1 {
+                    eprintln!(
+                        "task {task_id}: {status} ({}s elapsed)",
+                        started.elapsed().as_secs()
+                    );
+                }
+            }
+            Err(err) if retry::is_transient_error(&err) => {
+                eprintln!(
+                    "warning: poll for task {task_id} failed: {}",
+                    err.root_cause()
+                );
+            }
+            Err(err) => return Err(err),
+        }
+        let remaining = wait.timeout.saturating_sub(started.elapsed());
+        if remaining.is_zero() {
+            return Err(anyhow!(
+                "research task {task_id} still {status} after {}s (--wait-timeout)",
+                wait.timeout.as_secs()
+            ));
+        }
+        tokio::time::sleep(wait.interval.min(remaining)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_api, testserver::serve};
+
+    fn quick_wait() -> Wait {
+        Wait {
+            timeout: Duration::from_secs(10),
+            interval: Duration::from_millis(10),
+            poll_timeout: Some(Duration::from_millis(200)),
+        }
+    }
+
+    const SLOW: Duration = Duration::from_millis(600);
+    const RUNNING: &str = r#"{"status":"running"}"#;
+    const DONE: &str = r#"{"status":"completed","output":"report"}"#;
+
+    #[tokio::test]
+    async fn slow_poll_then_success_returns_the_task() {
+        let base = serve(vec![(SLOW, 200, RUNNING), (Duration::ZERO, 200, DONE)]);
+        let api = test_api(&base, 5);
+        let task = wait(&api, "t1", &quick_wait())
+            .await
+            .expect("task completes");
+        assert_eq!(task["status"], "completed");
+        assert_eq!(task["output"], "report");
+    }
+
+    #[tokio::test]
+    async fn poll_timeouts_do_not_open_the_breaker() {
+        let mut script = vec![(SLOW, 200, RUNNING); 3];
+        script.push((Duration::ZERO, 200, DONE));
+        let base = serve(script);
+        let api = test_api(&base, 2);
+        let task = wait(&api, "t2", &quick_wait())
+            .await
+            .expect("task completes");
+        assert_eq!(task["status"], "completed");
+        assert!(!api.breaker.is_open());
+        assert_eq!(api.breaker.skipped(), 0);
+    }
+
+    #[tokio::test]
+    async fn non_terminal_status_keeps_polling() {
+        let base = serve(vec![
+            (Duration::ZERO, 200, RUNNING),
+            (Duration::ZERO, 200, RUNNING),
+            (Duration::ZERO, 200, DONE),
+        ]);
+        let api = test_api(&base, 5);
+        let task = wait(&api, "t3", &quick_wait())
+            .await
+            .expect("task completes");
+        assert_eq!(task["status"], "completed");
+    }
+
+    #[tokio::test]
+    async fn client_error_stops_waiting() {
+        let base = serve(vec![(Duration::ZERO, 404, r#"{"error":"not found"}"#)]);
+        let api = test_api(&base, 5);
+        let err = wait(&api, "missing", &quick_wait())
+            .await
+            .expect_err("404 is not retried");
+        assert!(err.to_string().contains("404"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn wait_timeout_reports_the_last_status() {
+        let base = serve(vec![(Duration::ZERO, 200, RUNNING); 50]);
+        let api = test_api(&base, 5);
+        let wait_opts = Wait {
+            timeout: Duration::from_millis(100),
+            ..quick_wait()
+        };
+        let err = wait(&api, "t4", &wait_opts)
+            .await
+            .expect_err("task never finishes");
+        assert!(err.to_string().contains("still running"), "{err}");
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    fmt,
+    ops::RangeInclusive,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use reqwest::StatusCode;
+
+use crate::network::NetworkError;
+
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+
+/// Counts consecutive transient failures across every request of one run.
+/// Once `threshold` is reached the breaker stays open and further requests are
+/// refused without being sent.
+pub(crate) struct Breaker {
+    threshold: u32,
+    failures: AtomicU32,
+    skipped: AtomicUsize,
+}
+
+impl Breaker {
+    pub(crate) fn new(threshold: u32) -> Self {
+        Self {
+            threshold,
+            failures: AtomicU32::new(0),
+            skipped: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    pub(crate) fn is_open(&self) -> bool {
+        self.threshold > 0 && self.failures.load(Ordering::Relaxed) >= self.threshold
+    }
+
+    pub(crate) fn record(&self, transient_failure: bool) {
+        if self.is_open() {
+            return;
+        }
+        if transient_failure {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.failures.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn skip(&self) {
+        self.skipped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn skipped(&self) -> usize {
+        self.skipped.load(Ordering::Relaxed)
+    }
+}
+
+/// HTTP status codes that count as transient, from `--retry-on`.
+#[derive(Clone)]
+pub(crate) struct StatusSet(Vec<RangeInclusive<u16>>);
+
+impl Default for StatusSet {
+    fn default() -> Self {
+        Self(vec![429..=429, 500..=599])
+    }
+}
+
+impl StatusSet {
+    pub(crate) fn contains(&self, status: StatusCode) -> bool {
+        self.0.iter().any(|range| range.contains(&status.as_u16()))
+    }
+}
+
+impl fmt::Display for StatusSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self
+            .0
+            .iter()
+            .map(|range| {
+                if range.start() == range.end() {
+                    range.start().to_string()
+                } else {
+                    format!("{}-{}", range.start(), range.end())
+                }
+            })
+            .collect();
+        f.write_str(&parts.join(","))
+    }
+}
+
+/// Parses `429,500-504` style lists.
+pub(crate) fn parse_status_set(raw: &str) -> Result<StatusSet, String> {
+    let mut ranges = Vec::new();
+    for part in raw
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let (lo, hi) = part.split_once('-').unwrap_or((part, part));
+        let lo = parse_code(lo)?;
+        let hi = parse_code(hi)?;
+        if lo > hi {
+            return Err(format!("range {part:?} is reversed"));
+        }
+        ranges.push(lo..=hi);
+    }
+    if ranges.is_empty() {
+        return Err("expected at least one status code".to_string());
+    }
+    Ok(StatusSet(ranges))
+}
+
+fn parse_code(raw: &str) -> Result<u16, String> {
+    let raw = raw.trim();
+    match raw.parse::<u16>() {
+        Ok(code) if (100..=599).contains(&code) => Ok(code),
+        _ => Err(format!("{raw:?} is not an HTTP status code (100-599)")),
+    }
+}
+
+pub(crate) fn is_transient_error(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<NetworkError>().is_some()
+        || err
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|err| err.is_timeout() || err.is_connect())
+}
+
+pub(crate) fn backoff(attempt: u32) -> Duration {
+    BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaker_opens_after_threshold_consecutive_failures() {
+        let breaker = Breaker::new(3);
+        breaker.record(true);
+        breaker.record(true);
+        assert!(!breaker.is_open());
+        breaker.record(true);
+        assert!(breaker.is_open());
+    }
+
+    #[test]
+    fn breaker_success_resets_the_count() {
+        let breaker = Breaker::new(2);
+        breaker.record(true);
+        breaker.record(false);
+        breaker.record(true);
+        assert!(!breaker.is_open());
+        breaker.record(true);
+        assert!(breaker.is_open());
+    }
+
+    #[test]
+    fn open_breaker_stays_open_and_counts_skips() {
+        let breaker = Breaker::new(1);
+        breaker.record(true);
+        breaker.record(false);
+        assert!(breaker.is_open());
+        breaker.skip();
+        breaker.skip();
+        assert_eq!(breaker.skipped(), 2);
+    }
+
+    #[test]
+    fn zero_threshold_never_opens() {
+        let breaker = Breaker::new(0);
+        for _ in 0..100 {
+            breaker.record(true);
+        }
+        assert!(!breaker.is_open());
+    }
+
+    #[test]
+    fn status_set_parses_codes_and_ranges() {
+        let set = parse_status_set("429, 502-504").expect("valid list");
+        assert!(set.contains(StatusCode::TOO_MANY_REQUESTS));
+        assert!(set.contains(StatusCode::GATEWAY_TIMEOUT));
+        assert!(!set.contains(StatusCode::INTERNAL_SERVER_ERROR));
+        assert_eq!(set.to_string(), "429,502-504");
+    }
+
+    #[test]
+    fn status_set_rejects_bad_input() {
+        assert!(parse_status_set("").is_err());
+        assert!(parse_status_set("504-502").is_err());
+        assert!(parse_status_set("700").is_err());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        assert_eq!(backoff(0), Duration::from_millis(500));
+        assert_eq!(backoff(2), Duration::from_secs(2));
+        assert_eq!(backoff(10), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,333 @@
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+
+pub(crate) struct Param {
+    pub(crate) name: &'static str,
+    kind: &'static str,
+    required: bool,
+    description: &'static str,
+}
+
+const fn param(
+    name: &'static str,
+    kind: &'static str,
+    required: bool,
+    description: &'static str,
+) -> Param {
+    Param {
+        name,
+        kind,
+        required,
+        description,
+    }
+}
+
+const CONTENTS_OPTIONS: [Param; 7] = [
+    param(
+        "text",
+        "boolean|object",
+        false,
+        "Return page text; object form takes maxCharacters, includeHtmlTags",
+    ),
+    param(
+        "highlights",
+        "object",
+        false,
+        "Return key excerpts; takes numSentences, highlightsPerUrl, query",
+    ),
+    param(
+        "summary",
+        "boolean|object",
+        false,
+        "Return an LLM summary; object form takes query, schema",
+    ),
+    param(
+        "livecrawl",
+        "string",
+        false,
+        "never, fallback, always or preferred",
+    ),
+    param(
+        "livecrawlTimeout",
+        "integer",
+        false,
+        "Livecrawl timeout in milliseconds",
+    ),
+    param(
+        "subpages",
+        "integer",
+        false,
+        "Number of subpages to crawl per result",
+    ),
+    param(
+        "extras",
+        "object",
+        false,
+        "Extra data such as links and imageLinks counts",
+    ),
+];
+
+const SEARCH: [Param; 15] = [
+    param("query", "string", true, "The search query"),
+    param("type", "string", false, "auto, neural, keyword or fast"),
+    param(
+        "category",
+        "string",
+        false,
+        "Focus category such as company, news, research paper, pdf, github, tweet",
+    ),
+    param(
+        "userLocation",
+        "string",
+        false,
+        "Two-letter ISO country code to bias results",
+    ),
+    param(
+        "numResults",
+        "integer",
+        false,
+        "Number of results to return",
+    ),
+    param(
+        "includeDomains",
+        "string[]",
+        false,
+        "Only return results from these domains",
+    ),
+    param(
+        "excludeDomains",
+        "string[]",
+        false,
+        "Never return results from these domains",
+    ),
+    param(
+        "startCrawlDate",
+        "string",
+        false,
+        "ISO 8601 date; results crawled after it",
+    ),
+    param(
+        "endCrawlDate",
+        "string",
+        false,
+        "ISO 8601 date; results crawled before it",
+    ),
+    param(
+        "startPublishedDate",
+        "string",
+        false,
+        "ISO 8601 date; results published after it",
+    ),
+    param(
+        "endPublishedDate",
+        "string",
+        false,
+        "ISO 8601 date; results published before it",
+    ),
+    param(
+        "includeText",
+        "string[]",
+        false,
+        "Strings that must appear in the page text",
+    ),
+    param(
+        "excludeText",
+        "string[]",
+        false,
+        "Strings that must not appear in the page text",
+    ),
+    param(
+        "contents",
+        "object",
+        false,
+        "Contents to return per result (see `exa schema contents`)",
+    ),
+    param("moderation", "boolean", false, "Filter unsafe content"),
+];
+
+const CONTENTS: [Param; 3] = [
+    param("urls", "string[]", true, "URLs to fetch contents for"),
+    param(
+        "ids",
+        "string[]",
+        false,
+        "Result ids to fetch (alternative to urls)",
+    ),
+    param(
+        "subpageTarget",
+        "string|string[]",
+        false,
+        "Keywords used to pick subpages",
+    ),
+];
+
+const FIND_SIMILAR: [Param; 12] = [
+    param("url", "string", true, "The page to find similar links for"),
+    param(
+        "numResults",
+        "integer",
+        false,
+        "Number of results to return",
+    ),
+    param(
+        "category",
+        "string",
+        false,
+        "Focus category such as company, news, research paper",
+    ),
+    param(
+        "includeDomains",
+        "string[]",
+        false,
+        "Only return results from these domains",
+    ),
+    param(
+        "excludeDomains",
+        "string[]",
+        false,
+        "Never return results from these domains",
+    ),
+    param(
+        "excludeSourceDomain",
+        "boolean",
+        false,
+        "Leave out results from the source url's domain",
+    ),
+    param(
+        "startCrawlDate",
+        "string",
+        false,
+        "ISO 8601 date; results crawled after it",
+    ),
+    param(
+        "endCrawlDate",
+        "string",
+        false,
+        "ISO 8601 date; results crawled before it",
+    ),
+    param(
+        "startPublishedDate",
+        "string",
+        false,
+        "ISO 8601 date; results published after it",
+    ),
+    param(
+        "endPublishedDate",
+        "string",
+        false,
+        "ISO 8601 date; results published before it",
+    ),
+    param(
+        "includeText",
+        "string[]",
+        false,
+        "Strings that must appear in the page text",
+    ),
+    param(
+        "contents",
+        "object",
+        false,
+        "Contents to return per result (see `exa schema contents`)",
+    ),
+];
+
+const ANSWER: [Param; 5] = [
+    param("query", "string", true, "The question to answer"),
+    param("model", "string", false, "exa or exa-pro"),
+    param(
+        "systemPrompt",
+        "string",
+        false,
+        "Instructions that steer the answer's style",
+    ),
+    param(
+        "stream",
+        "boolean",
+        false,
+        "Stream the answer as server-sent events",
+    ),
+    param(
+        "text",
+        "boolean",
+        false,
+        "Include full text of cited sources",
+    ),
+];
+
+const CONTEXT: [Param; 2] = [
+    param("query", "string", true, "What the code context is for"),
+    param(
+        "tokensNum",
+        "integer|string",
+        false,
+        "Token budget, or \"dynamic\"",
+    ),
+];
+
+const RESEARCH: [Param; 3] = [
+    param("instructions", "string", true, "What to research"),
+    param("model", "string", false, "exa-research or exa-research-pro"),
+    param(
+        "output",
+        "object",
+        false,
+        "Output shape: schema (JSON Schema) or inferSchema",
+    ),
+];
+
+/// Endpoint name for a command name or alias.
+fn canonical(endpoint: &str) -> Option<&'static str> {
+    Some(match endpoint {
+        "search" | "s" => "search",
+        "contents" | "c" => "contents",
+        "find-similar" | "findSimilar" | "fs" => "find-similar",
+        "answer" | "a" => "answer",
+        "context" | "ctx" => "context",
+        "research" | "r" => "research",
+        _ => return None,
+    })
+}
+
+/// Known request parameters for `endpoint`, top-level fields first.
+pub(crate) fn params(endpoint: &str) -> Option<Vec<&'static Param>> {
+    let params: Vec<&Param> = match canonical(endpoint)? {
+        "search" => SEARCH.iter().collect(),
+        "contents" => CONTENTS.iter().chain(&CONTENTS_OPTIONS).collect(),
+        "find-similar" => FIND_SIMILAR.iter().collect(),
+        "answer" => ANSWER.iter().collect(),
+        "context" => CONTEXT.iter().collect(),
+        _ => RESEARCH.iter().collect(),
+    };
+    Some(params)
+}
+
+pub(crate) fn print(endpoint: &str, as_json: bool) -> Result<()> {
+    let params = params(endpoint).ok_or_else(|| {
+        anyhow!("unknown endpoint {endpoint:?} (search, contents, find-similar, answer, context, research)")
+    })?;
+    if as_json {
+        let rows: Vec<Value> = params
+            .iter()
+            .map(|param| {
+                json!({
+                    "name": param.name,
+                    "type": param.kind,
+                    "required": param.required,
+                    "description": param.description,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        return Ok(());
+    }
+    let name_width = params.iter().map(|p| p.name.len()).max().unwrap_or(0);
+    let kind_width = params.iter().map(|p| p.kind.len()).max().unwrap_or(0);
+    for param in params {
+        let required = if param.required { " (required)" } else { "" };
+        println!(
+            "{:<name_width$}  {:<kind_width$}  {}{required}",
+            param.name, param.kind, param.description
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+//! A scripted HTTP server for tests that need real requests.
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// `(path, body)` of every request served so far, in arrival order.
+pub(crate) type Requests = Arc<Mutex<Vec<(String, String)>>>;
+
+/// Serves one scripted response per connection, in order: each `(delay,
+/// status, body)` is sent after sleeping `delay`. Returns the base url.
+pub(crate) fn serve(script: Vec<(Duration, u16, &'static str)>) -> String {
+    serve_recorded(script).0
+}
+
+/// [`serve`], also returning the requests it received.
+pub(crate) fn serve_recorded(script: Vec<(Duration, u16, &'static str)>) -> (String, Requests) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind test server");
+    let base = format!("http://{}", listener.local_addr().expect("local addr"));
+    let requests = Requests::default();
+    let seen = Arc::clone(&requests);
+    thread::spawn(move || {
+        for (delay, status, body) in script {
+            let Ok((stream, _)) = listener.accept() else {
+                return;
+            };
+            let seen = Arc::clone(&seen);
+            thread::spawn(move || respond(stream, &seen, delay, status, body));
+        }
+    });
+    (base, requests)
+}
+
+fn respond(mut stream: TcpStream, seen: &Requests, delay: Duration, status: u16, body: &str) {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    let head_end = loop {
+        if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => request.extend_from_slice(&buf[..n]),
+        }
+    };
+    let head = String::from_utf8_lossy(&request[..head_end]).into_owned();
+    let length = head
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("content-length")
+                .then(|| value.trim().parse::<usize>().ok())
+                .flatten()
+        })
+        .unwrap_or(0);
+    while request.len() < head_end + length {
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => request.extend_from_slice(&buf[..n]),
+        }
+    }
+    let path = head.split_whitespace().nth(1).unwrap_or("").to_string();
+    let sent = String::from_utf8_lossy(&request[head_end..]).into_owned();
+    if let Ok(mut seen) = seen.lock() {
+        seen.push((path, sent));
+    }
+    thread::sleep(delay);
+    let response = format!(
+        "HTTP/1.1 {status} X\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
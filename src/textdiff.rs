@@ -0,0 +1,96 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+
+use crate::config;
+
+/// Hashes each result's text, compares it with the hash stored by the last
+/// run for the same url and stores the new one. Returns the urls split into
+/// `added`, `changed` and `unchanged`.
+pub(crate) fn compare(results: &[Value]) -> Result<Value> {
+    compare_in(&cache_file("text-hashes.json")?, results)
+}
+
+fn compare_in(path: &Path, results: &[Value]) -> Result<Value> {
+    let mut hashes: BTreeMap<String, String> = load(path)?;
+    let (mut added, mut changed, mut unchanged) = (Vec::new(), Vec::new(), Vec::new());
+    for result in results {
+        let Some(url) = result.get("url").and_then(Value::as_str) else {
+            continue;
+        };
+        let text = result.get("text").and_then(Value::as_str).unwrap_or("");
+        let hash = text_hash(text);
+        match hashes.insert(url.to_string(), hash.clone()) {
+            None => added.push(url),
+            Some(previous) if previous != hash => changed.push(url),
+            Some(_) => unchanged.push(url),
+        }
+    }
+    save(path, &hashes)?;
+    Ok(json!({ "added": added, "changed": changed, "unchanged": unchanged }))
+}
+
+fn load<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let raw = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    serde_json::from_str(&raw).map_err(|err| anyhow!("parse {}: {err}", path.display()))
+}
+
+fn save<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+    }
+    fs::write(path, serde_json::to_string_pretty(value)?)
+        .with_context(|| format!("write {}", path.display()))
+}
+
+fn text_hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn cache_file(name: &str) -> Result<PathBuf> {
+    config::cache_dir()
+        .map(|dir| dir.join(name))
+        .context("cannot locate cache dir (set HOME or XDG_CACHE_HOME)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_splits_added_changed_and_unchanged_text() {
+        let dir = std::env::temp_dir().join(format!("exa-text-hashes-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("text-hashes.json");
+        let page = |url: &str, text: &str| json!({ "url": url, "text": text });
+
+        let first = [page("https://a.com", "one"), page("https://b.com", "two")];
+        let changes = compare_in(&path, &first).unwrap();
+        assert_eq!(changes["added"], json!(["https://a.com", "https://b.com"]));
+
+        let second = [page("https://a.com", "one"), page("https://b.com", "two!")];
+        let changes = compare_in(&path, &second).unwrap();
+        assert_eq!(changes["added"], json!([]));
+        assert_eq!(changes["changed"], json!(["https://b.com"]));
+        assert_eq!(changes["unchanged"], json!(["https://a.com"]));
+
+        let changes = compare_in(&path, &second).unwrap();
+        assert_eq!(
+            changes["unchanged"],
+            json!(["https://a.com", "https://b.com"])
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
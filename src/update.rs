@@ -0,0 +1,196 @@
+use std::{fs, path::Path, process::Command};
+
+use anyhow::{Context, Result, anyhow};
+use reqwest::Client;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Everything `self-update` fetches: release metadata from `RELEASES_API`,
+/// and assets under `DOWNLOAD_PREFIX` (which GitHub redirects to its
+/// download CDN). Any other URL is refused.
+const RELEASES_API: &str = "https://api.github.com/repos/radjathaher/exa-cli/releases/latest";
+const DOWNLOAD_PREFIX: &str = "https://github.com/radjathaher/exa-cli/releases/download/";
+const BIN_NAME: &str = "exa";
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+    /// `sha256:<hex>`, computed by GitHub for each uploaded asset.
+    digest: Option<String>,
+}
+
+/// Checks the latest release and, unless `check_only`, replaces the running
+/// binary with it after verifying its SHA-256.
+pub(crate) async fn run(client: &Client, check_only: bool) -> Result<i32> {
+    let current = env!("CARGO_PKG_VERSION");
+    let release: Release = get(client, RELEASES_API)
+        .await?
+        .json()
+        .await
+        .context("parse latest release")?;
+    let latest = release.tag_name.trim_start_matches('v');
+    let (Some(have), Some(want)) = (parse_version(current), parse_version(latest)) else {
+        return Err(anyhow!(
+            "cannot compare versions {current:?} and {:?}",
+            release.tag_name
+        ));
+    };
+    if want <= have {
+        println!("exa {current} is up to date (latest release {latest})");
+        return Ok(0);
+    }
+    if check_only {
+        println!("update available: exa {current} -> {latest}");
+        return Ok(0);
+    }
+
+    let target = target()?;
+    let suffix = format!("-{target}.tar.gz");
+    let asset = release
+        .assets
+        .iter()
+        .find(|asset| asset.name.ends_with(&suffix))
+        .ok_or_else(|| anyhow!("release {} has no asset for {target}", release.tag_name))?;
+    let expected = expected_sha256(client, &release, asset).await?;
+    let archive = get(client, &asset.browser_download_url)
+        .await?
+        .bytes()
+        .await
+        .with_context(|| format!("download {}", asset.name))?;
+    let actual = hex(&Sha256::digest(&archive));
+    if actual != expected {
+        return Err(anyhow!(
+            "checksum mismatch for {}: expected {expected}, got {actual}; not updating",
+            asset.name
+        ));
+    }
+
+    let exe = std::env::current_exe().context("locate the running exa binary")?;
+    let work = std::env::temp_dir().join(format!("exa-update-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&work).with_context(|| format!("create {}", work.display()))?;
+    let result = install(&work, &archive, &exe);
+    let _ = fs::remove_dir_all(&work);
+    result?;
+    println!("updated exa {current} -> {latest} ({})", exe.display());
+    Ok(0)
+}
+
+/// Unpacks `archive` in `work` and renames its binary over `exe`. The new
+/// file is staged beside `exe` so the rename stays on one filesystem.
+fn install(work: &Path, archive: &[u8], exe: &Path) -> Result<()> {
+    let tarball = work.join("exa.tar.gz");
+    fs::write(&tarball, archive).with_context(|| format!("write {}", tarball.display()))?;
+    let status = Command::new("tar")
+        .arg("-xzf")
+        .arg(&tarball)
+        .arg("-C")
+        .arg(work)
+        .status()
+        .context("run tar")?;
+    if !status.success() {
+        return Err(anyhow!("tar failed to unpack the release ({status})"));
+    }
+    let unpacked = work.join(BIN_NAME);
+    if !unpacked.is_file() {
+        return Err(anyhow!("release archive has no {BIN_NAME} binary"));
+    }
+    let dir = exe
+        .parent()
+        .ok_or_else(|| anyhow!("{} has no parent directory", exe.display()))?;
+    let staged = dir.join(format!(".{BIN_NAME}.update-{}", std::process::id()));
+    fs::copy(&unpacked, &staged).with_context(|| format!("write {}", staged.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("chmod {}", staged.display()))?;
+    }
+    if let Err(err) = fs::rename(&staged, exe) {
+        let _ = fs::remove_file(&staged);
+        return Err(err).with_context(|| format!("replace {}", exe.display()));
+    }
+    Ok(())
+}
+
+/// The asset's SHA-256 from GitHub's `digest`, or else from a
+/// `<asset>.sha256` file in the same release.
+async fn expected_sha256(client: &Client, release: &Release, asset: &Asset) -> Result<String> {
+    if let Some(hex) = asset
+        .digest
+        .as_deref()
+        .and_then(|digest| digest.strip_prefix("sha256:"))
+    {
+        return Ok(hex.to_ascii_lowercase());
+    }
+    let sums = format!("{}.sha256", asset.name);
+    let sums = release
+        .assets
+        .iter()
+        .find(|other| other.name == sums)
+        .ok_or_else(|| anyhow!("release has no checksum for {}; not updating", asset.name))?;
+    let text = get(client, &sums.browser_download_url)
+        .await?
+        .text()
+        .await
+        .with_context(|| format!("download {}", sums.name))?;
+    text.split_whitespace()
+        .next()
+        .map(str::to_ascii_lowercase)
+        .ok_or_else(|| anyhow!("{} is empty", sums.name))
+}
+
+async fn get(client: &Client, url: &str) -> Result<reqwest::Response> {
+    if url != RELEASES_API && !url.starts_with(DOWNLOAD_PREFIX) {
+        return Err(anyhow!("refusing to download from {url}"));
+    }
+    let response = client
+        .get(url)
+        .header(
+            reqwest::header::USER_AGENT,
+            concat!("exa-cli/", env!("CARGO_PKG_VERSION")),
+        )
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .send()
+        .await
+        .with_context(|| format!("request {url}"))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(anyhow!("GET {url} failed status={status}"));
+    }
+    Ok(response)
+}
+
+/// The release asset suffix for this platform, as in scripts/install.sh.
+fn target() -> Result<String> {
+    let os = match std::env::consts::OS {
+        "macos" => "darwin",
+        "linux" => "linux",
+        other => return Err(anyhow!("no release builds for {other}")),
+    };
+    let arch = match std::env::consts::ARCH {
+        "aarch64" => "aarch64",
+        "x86_64" => "x86_64",
+        other => return Err(anyhow!("no release builds for {other}")),
+    };
+    Ok(format!("{os}-{arch}"))
+}
+
+/// `MAJOR.MINOR.PATCH`, ignoring any `-pre` or `+build` suffix.
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    let version = (parts.next()??, parts.next()??, parts.next()??);
+    parts.next().is_none().then_some(version)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}